@@ -0,0 +1,47 @@
+//! Measures `pretty_size` across every unit from B to EiB against a hand-written baseline
+//! that only picks the unit. Run with `cargo bench -p kondo-lib --bench pretty_size`.
+
+use kondo_lib::pretty_size;
+use std::{hint::black_box, time};
+
+const ITERATIONS: u32 = 1_000_000;
+
+const SIZES: [u64; 7] = [
+    512,
+    3 * 1024,
+    5 * 1_048_576,
+    7 * 1_073_741_824,
+    11 * 1_099_511_627_776,
+    13 * 1_125_899_906_842_624,
+    15 * 1_152_921_504_606_846_976,
+];
+
+fn baseline(size: u64) -> &'static str {
+    const UNITS: [&str; 7] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+    let unit = if size == 0 {
+        0
+    } else {
+        (63 - size.leading_zeros()) / 10
+    };
+    UNITS[unit as usize]
+}
+
+fn bench<T, F: Fn(u64) -> T>(name: &str, f: F) {
+    for size in SIZES.iter().copied() {
+        let start = time::Instant::now();
+        for _ in 0..ITERATIONS {
+            black_box(f(black_box(size)));
+        }
+        println!(
+            "{:<12} {:>20} {:?}/call",
+            name,
+            size,
+            start.elapsed() / ITERATIONS
+        );
+    }
+}
+
+fn main() {
+    bench("pretty_size", pretty_size);
+    bench("baseline", baseline);
+}
@@ -0,0 +1,53 @@
+//! Compares `Project::size` against `Project::size_parallel` on a Unity-like project with
+//! seven artifact directories of equal size. Run with `cargo bench -p kondo-lib`.
+
+use kondo_lib::Project;
+use std::{fs, path, time};
+
+const DIRS: [&str; 7] = [
+    "Library",
+    "Temp",
+    "Obj",
+    "Logs",
+    "MemoryCaptures",
+    "Build",
+    "Builds",
+];
+const SUBDIRS_PER_DIR: usize = 20;
+const FILES_PER_SUBDIR: usize = 50;
+const ITERATIONS: u32 = 20;
+
+fn create_fixture(root: &path::Path) -> std::io::Result<()> {
+    fs::create_dir_all(root)?;
+    fs::write(root.join("Assembly-CSharp.csproj"), "")?;
+    for dir in DIRS.iter() {
+        for sub in 0..SUBDIRS_PER_DIR {
+            let sub_dir = root.join(dir).join(sub.to_string());
+            fs::create_dir_all(&sub_dir)?;
+            for file in 0..FILES_PER_SUBDIR {
+                fs::write(sub_dir.join(file.to_string()), [0u8; 512])?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn bench<F: Fn() -> u64>(name: &str, f: F) {
+    let expected = f();
+    let start = time::Instant::now();
+    for _ in 0..ITERATIONS {
+        assert_eq!(f(), expected);
+    }
+    println!("{:<16} {:?}/iter", name, start.elapsed() / ITERATIONS);
+}
+
+fn main() -> std::io::Result<()> {
+    let root = std::env::temp_dir().join(format!("kondo-bench-size-{}", std::process::id()));
+    create_fixture(&root)?;
+
+    let project = Project::detect_in(&root).expect("fixture is a Unity project");
+    bench("size", || project.size());
+    bench("size_parallel", || project.size_parallel());
+
+    fs::remove_dir_all(&root)
+}
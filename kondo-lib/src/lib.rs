@@ -1,15 +1,23 @@
 use jwalk::Parallelism;
-use rayon::prelude::*;
-use std::borrow::Borrow;
-use std::fmt::Error;
+use rayon::{iter::Either, prelude::*};
 use std::iter::FromIterator;
-use std::path::PathBuf;
 use std::{
+    borrow::Cow,
+    collections::HashMap,
     error::{self, Error},
-    fs, path,
+    fmt, fs, io, path, time,
 };
 
+/// The commonly used types and functions, for `use kondo_lib::prelude::*;`
+pub mod prelude {
+    pub use super::{
+        dir_size, pretty_size, scan, scan_with_options, MultiError, Project, ProjectSize,
+        ProjectType, Red, ScanOptions,
+    };
+}
+
 const SYMLINK_FOLLOW: bool = true;
+const CASE_INSENSITIVE_FS: bool = cfg!(any(windows, target_os = "macos"));
 
 const FILE_CARGO_TOML: &str = "Cargo.toml";
 const FILE_PACKAGE_JSON: &str = "package.json";
@@ -22,6 +30,50 @@ const FILE_UNREAL_SUFFIX: &str = ".uproject";
 const FILE_JUPYTER_SUFFIX: &str = ".ipynb";
 const FILE_PYTHON_SUFFIX: &str = ".py";
 const FILE_COMPOSER_JSON: &str = "composer.json";
+const FILE_BUCK_CONFIG: &str = ".buckconfig";
+const FILE_BUCK_BUILD: &str = "BUCK";
+const FILE_PANTS_TOML: &str = "pants.toml";
+const FILE_PYPROJECT_TOML: &str = "pyproject.toml";
+const FILE_PDM_LOCK: &str = "pdm.lock";
+const FILE_HATCH_TOML: &str = "hatch.toml";
+const FILE_NIX_DEFAULT: &str = "default.nix";
+const FILE_NIX_SHELL: &str = "shell.nix";
+const FILE_NIX_FLAKE: &str = "flake.nix";
+const FILE_RYE_LOCK: &str = "rye.lock";
+const FILE_NX_JSON: &str = "nx.json";
+const FILE_TURBO_JSON: &str = "turbo.json";
+/// Written to the project directory by `Project::save_metadata`
+const FILE_KONDO_META: &str = ".kondo_meta";
+const KONDO_META_LAST_CLEANED: &str = "last_cleaned";
+
+/// Every file name matched exactly (not by suffix), used for case insensitive detection
+const PROJECT_FILES: [&str; 20] = [
+    FILE_CARGO_TOML,
+    FILE_PACKAGE_JSON,
+    FILE_ASSEMBLY_CSHARP,
+    FILE_STACK_HASKELL,
+    FILE_SBT_BUILD,
+    FILE_MVN_BUILD,
+    FILE_CMAKE_BUILD,
+    FILE_COMPOSER_JSON,
+    FILE_BUCK_CONFIG,
+    FILE_BUCK_BUILD,
+    FILE_PANTS_TOML,
+    FILE_PYPROJECT_TOML,
+    FILE_PDM_LOCK,
+    FILE_HATCH_TOML,
+    FILE_NIX_DEFAULT,
+    FILE_NIX_SHELL,
+    FILE_NIX_FLAKE,
+    FILE_RYE_LOCK,
+    FILE_NX_JSON,
+    FILE_TURBO_JSON,
+];
+
+const CARGO_WORKSPACE_SECTION: &str = "[workspace]";
+const PYPROJECT_POETRY_SECTION: &str = "[tool.poetry]";
+const PYPROJECT_PDM_SECTION: &str = "[tool.pdm]";
+const PYPROJECT_RYE_SECTION: &str = "[tool.rye]";
 
 const PROJECT_CARGO_DIRS: [&str; 1] = ["target"];
 const PROJECT_NODE_DIRS: [&str; 1] = ["node_modules"];
@@ -48,8 +100,34 @@ const PROJECT_UNREAL_DIRS: [&str; 5] = [
 const PROJECT_JUPYTER_DIRS: [&str; 1] = [".ipynb_checkpoints"];
 const PROJECT_PYTHON_DIRS: [&str; 3] = ["__pycache__", "__pypackages__", ".venv"];
 const PROJECT_COMPOSER_DIRS: [&str; 1] = ["vendor"];
+const PROJECT_BUCK_DIRS: [&str; 1] = ["buck-out"];
+const PROJECT_PANTS_DIRS: [&str; 2] = [".pants.d", "dist"];
+const PROJECT_POETRY_DIRS: [&str; 3] = [".venv", "__pycache__", "dist"];
+const PROJECT_PDM_DIRS: [&str; 3] = [".pdm-python", "__pycache__", ".venv"];
+/// Artifacts that are plain files rather than directories
+const PROJECT_PDM_FILES: [&str; 1] = [".pdm-python"];
+const PROJECT_HATCH_DIRS: [&str; 3] = [".hatch", "dist", "__pycache__"];
+const PROJECT_NIX_DIRS: [&str; 2] = ["result", ".direnv"];
+const PROJECT_RYE_DIRS: [&str; 3] = [".venv", "dist", "__pycache__"];
+const PROJECT_NX_DIRS: [&str; 3] = [".nx", "dist", "node_modules"];
+
+const CARGO_INCREMENTAL_DIRS: [&str; 2] =
+    ["target/debug/incremental", "target/release/incremental"];
+const PROJECT_TURBOREPO_DIRS: [&str; 2] = [".turbo", "node_modules"];
+
+const GLOBAL_CACHE_NX_DIRS: [&str; 1] = [".nx"];
+const GLOBAL_CACHE_TURBOREPO_DIRS: [&str; 1] = [".turbo"];
+
+const LOCKFILES_CARGO: [&str; 1] = ["Cargo.lock"];
+const LOCKFILES_NODE: [&str; 3] = ["package-lock.json", "yarn.lock", "pnpm-lock.yaml"];
+const LOCKFILES_COMPOSER: [&str; 1] = ["composer.lock"];
+
+const AUDIT_LARGE_ARTIFACT_SIZE: u64 = 1_073_741_824;
+const AUDIT_STALE_DAYS: u32 = 30;
+const AUDIT_DEPENDENCY_COUNT: usize = 1000;
 
 const PROJECT_CARGO_NAME: &str = "Cargo";
+const PROJECT_CARGO_WORKSPACE_NAME: &str = "Cargo Workspace";
 const PROJECT_NODE_NAME: &str = "Node";
 const PROJECT_UNITY_NAME: &str = "Unity";
 const PROJECT_STACK_NAME: &str = "Stack";
@@ -60,10 +138,22 @@ const PROJECT_UNREAL_NAME: &str = "Unreal";
 const PROJECT_JUPYTER_NAME: &str = "Jupyter";
 const PROJECT_PYTHON_NAME: &str = "Python";
 const PROJECT_COMPOSER_NAME: &str = "Composer";
+const PROJECT_BUCK_NAME: &str = "Buck";
+const PROJECT_PANTS_NAME: &str = "Pants";
+const PROJECT_POETRY_NAME: &str = "Poetry";
+const PROJECT_PDM_NAME: &str = "PDM";
+const PROJECT_HATCH_NAME: &str = "Hatch";
+const PROJECT_NIX_NAME: &str = "Nix";
+const PROJECT_RYE_NAME: &str = "Rye";
+const PROJECT_NX_NAME: &str = "Nx";
+const PROJECT_TURBOREPO_NAME: &str = "Turborepo";
 
 #[derive(Debug, Clone)]
 pub enum ProjectType {
     Cargo,
+    /// A Cargo workspace root with its members merged in, only produced by
+    /// `ScanOptions::merge_cargo_workspaces`
+    CargoWorkspace,
     Node,
     Unity,
     Stack,
@@ -71,18 +161,96 @@ pub enum ProjectType {
     SBT,
     Maven,
     CMake,
+    /// Detected by any `*.uproject` file. A directory holding several `.uproject` files is
+    /// still a single project, they all share the directory's artifact dirs.
     Unreal,
     Jupyter,
     Python,
     Composer,
+    Buck,
+    Pants,
+    Poetry,
+    #[allow(clippy::upper_case_acronyms)]
+    PDM,
+    Hatch,
+    Nix,
+    Rye,
+    /// Detected by `nx.json`. Nx workspaces usually have a `package.json` too, the more
+    /// specific `nx.json` wins, and `node_modules` is cleaned along with the Nx cache.
+    Nx,
+    /// Detected by `turbo.json`, which wins over `package.json` like Nx's `nx.json` does, and
+    /// `node_modules` is cleaned along with `.turbo`
+    Turborepo,
 }
 
 #[derive(Debug, Clone)]
 pub struct Project {
     pub project_type: ProjectType,
     pub path: path::PathBuf,
+    /// Artifact size measured while scanning, see `ScanOptions::compute_size_during_scan`.
+    /// `size` always measures afresh.
+    pub pre_computed_size: Option<u64>,
+    /// `compute_artifact_hash` taken while scanning, see `ScanOptions::compute_artifact_hash`
+    pub artifact_dir_hash: Option<u64>,
+    /// When the project was last cleaned, as recorded by `save_metadata`. Only read while
+    /// scanning with `ScanOptions::load_metadata`, or by `load_metadata`.
+    pub last_cleaned: Option<time::SystemTime>,
+}
+
+/// Builds a `Project` of a given type without detecting it, e.g. for test fixtures
+#[derive(Debug, Clone, Default)]
+pub struct ProjectBuilder {
+    path: Option<path::PathBuf>,
+    project_type: Option<ProjectType>,
+}
+
+impl ProjectBuilder {
+    pub fn new() -> Self {
+        ProjectBuilder::default()
+    }
+
+    pub fn path(mut self, p: impl Into<path::PathBuf>) -> Self {
+        self.path = Some(p.into());
+        self
+    }
+
+    pub fn project_type(mut self, t: ProjectType) -> Self {
+        self.project_type = Some(t);
+        self
+    }
+
+    /// Fails if the path or project type weren't set, or the path doesn't exist
+    pub fn build(self) -> Result<Project, BuildError> {
+        let path = self.path.ok_or(BuildError::MissingPath)?;
+        let project_type = self.project_type.ok_or(BuildError::MissingProjectType)?;
+        if !path.exists() {
+            return Err(BuildError::PathNotFound(path));
+        }
+        Ok(Project::new(project_type, path))
+    }
+}
+
+#[derive(Debug)]
+pub enum BuildError {
+    MissingPath,
+    MissingProjectType,
+    PathNotFound(path::PathBuf),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::MissingPath => write!(f, "no project path set"),
+            BuildError::MissingProjectType => write!(f, "no project type set"),
+            BuildError::PathNotFound(path) => {
+                write!(f, "project path {} does not exist", path.display())
+            }
+        }
+    }
 }
 
+impl Error for BuildError {}
+
 #[derive(Debug, Clone)]
 pub struct ProjectSize {
     pub artifact_size: u64,
@@ -90,10 +258,357 @@ pub struct ProjectSize {
     pub dirs: Vec<(String, u64, bool)>,
 }
 
+#[derive(Debug, Clone)]
+pub struct ArtifactDirPreview {
+    pub path: path::PathBuf,
+    pub size: u64,
+    pub file_count: u64,
+    pub exists: bool,
+}
+
+/// Describes what `Project::clean` would remove, without touching the filesystem
+#[derive(Debug, Clone)]
+pub struct CleanPreview {
+    pub dirs: Vec<ArtifactDirPreview>,
+}
+
+impl CleanPreview {
+    pub fn total_size(&self) -> u64 {
+        self.dirs.iter().map(|d| d.size).sum()
+    }
+}
+
+impl fmt::Display for CleanPreview {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for dir in &self.dirs {
+            if dir.exists {
+                writeln!(
+                    f,
+                    "{} ({}, {} files)",
+                    dir.path.display(),
+                    pretty_size(dir.size),
+                    dir.file_count
+                )?;
+            } else {
+                writeln!(f, "{} (missing)", dir.path.display())?;
+            }
+        }
+        write!(f, "total: {}", pretty_size(self.total_size()))
+    }
+}
+
+/// The length and mtime of every file in a project's artifact dirs at one point in time,
+/// see `Project::snapshot_artifact_hash`
+///
+/// Converts to and from text with `Display` and `FromStr`, one `len mtime_secs mtime_nanos
+/// path` line per file. Paths are relative to the project and stored lossily as UTF-8, files
+/// whose path contains a newline are left out.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArtifactSnapshot {
+    files: std::collections::BTreeMap<path::PathBuf, (u64, time::Duration)>,
+}
+
+impl ArtifactSnapshot {
+    /// A hash of the whole snapshot, equal for snapshots of unchanged artifacts
+    pub fn hash(&self) -> u64 {
+        use std::hash::{Hash, Hasher};
+
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        self.files.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+}
+
+impl fmt::Display for ArtifactSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (path, (len, mtime)) in &self.files {
+            writeln!(
+                f,
+                "{} {} {} {}",
+                len,
+                mtime.as_secs(),
+                mtime.subsec_nanos(),
+                path.to_string_lossy()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for ArtifactSnapshot {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut snapshot = ArtifactSnapshot::default();
+        for (i, line) in s.lines().enumerate().filter(|(_, l)| !l.is_empty()) {
+            let invalid = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid artifact snapshot line {}", i + 1),
+                )
+            };
+            let mut fields = line.splitn(4, ' ');
+            let mut number = || fields.next().and_then(|f| f.parse::<u64>().ok());
+            let len = number().ok_or_else(invalid)?;
+            let secs = number().ok_or_else(invalid)?;
+            let nanos = fields
+                .next()
+                .and_then(|f| f.parse::<u32>().ok())
+                .filter(|&nanos| nanos < 1_000_000_000)
+                .ok_or_else(invalid)?;
+            let path = fields.next().ok_or_else(invalid)?;
+            snapshot.files.insert(
+                path::PathBuf::from(path),
+                (len, time::Duration::new(secs, nanos)),
+            );
+        }
+        Ok(snapshot)
+    }
+}
+
+/// Files that changed between an `ArtifactSnapshot` and now, by path relative to the project
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArtifactDiff {
+    pub added: Vec<path::PathBuf>,
+    pub removed: Vec<path::PathBuf>,
+    /// Files whose length or mtime differ
+    pub modified: Vec<path::PathBuf>,
+}
+
+impl ArtifactDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MoveResult {
+    /// `(from, to)` for every artifact directory that was moved
+    pub moved_paths: Vec<(path::PathBuf, path::PathBuf)>,
+}
+
+#[derive(Debug)]
+pub struct MoveError {
+    pub from: path::PathBuf,
+    pub to: path::PathBuf,
+    pub error: io::Error,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "error moving {} to {}: {}",
+            self.from.display(),
+            self.to.display(),
+            self.error
+        )
+    }
+}
+
+impl Error for MoveError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SymlinkOptions {
+    /// Replace anything already at an artifact dir's path instead of failing
+    pub overwrite_existing: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CleanOptions {
+    /// Check that every artifact dir is writable before removing anything, see `Project::can_clean`
+    pub verify_permissions: bool,
+    /// Remove broken symlinks inside the artifact dirs first, listing each in
+    /// `CleanResult::removed`, see `Project::broken_symlink_paths`
+    pub remove_broken_symlinks: bool,
+    /// The order artifact dirs are removed in
+    pub priority_order: CleanOrder,
+    /// Measure each artifact dir before removing it, for `CleanResult::bytes_freed`. Sizes are
+    /// always measured when `priority_order` sorts by them, otherwise `bytes_freed` stays 0.
+    pub measure_sizes: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CleanOrder {
+    /// Biggest first, freeing the most space soonest when disk space is tight
+    LargestFirst,
+    SmallestFirst,
+    /// By path
+    Alphabetical,
+    /// The order of `Project::artifact_dirs`
+    #[default]
+    Default,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CleanResult {
+    pub bytes_freed: u64,
+    pub removed: Vec<path::PathBuf>,
+}
+
+/// Reported for each artifact dir by `Project::clean_with_callback`
+#[derive(Debug, Clone)]
+pub enum CleanEvent {
+    BeforeRemove { size: u64 },
+    AfterRemove { bytes_freed: u64 },
+    RemoveFailed { error: String },
+}
+
+#[derive(Debug)]
+pub enum CleanError {
+    PermissionDenied(path::PathBuf),
+    IOError {
+        path: path::PathBuf,
+        error: io::Error,
+    },
+}
+
+impl fmt::Display for CleanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CleanError::PermissionDenied(path) => {
+                write!(f, "no write permission for {}", path.display())
+            }
+            CleanError::IOError { path, error } => {
+                write!(f, "error removing {}: {}", path.display(), error)
+            }
+        }
+    }
+}
+
+impl Error for CleanError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CleanError::PermissionDenied(_) => None,
+            CleanError::IOError { error, .. } => Some(error),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct BatchCleanReport {
+    pub total_bytes_freed: u64,
+    pub projects_cleaned: usize,
+    pub projects_failed: usize,
+    pub errors: Vec<CleanError>,
+    pub duration: time::Duration,
+}
+
+impl BatchCleanReport {
+    /// A one line summary, e.g. "cleaned 42 projects, freed 15.3GiB, 3 errors"
+    pub fn display(&self) -> String {
+        format!(
+            "cleaned {} projects, freed {}, {} errors",
+            self.projects_cleaned,
+            pretty_size(self.total_bytes_freed),
+            self.errors.len()
+        )
+    }
+}
+
+/// A size in bytes along with its `pretty_size` rendering
+#[derive(Debug, Clone)]
+pub struct SizeValue {
+    pub bytes: u64,
+    pub pretty: String,
+}
+
+impl SizeValue {
+    pub fn new(bytes: u64) -> SizeValue {
+        SizeValue {
+            bytes,
+            pretty: pretty_size(bytes),
+        }
+    }
+
+    /// The size in binary units, e.g. `1.5KiB`
+    pub fn display_iec(&self) -> &str {
+        &self.pretty
+    }
+
+    /// The size in decimal units, e.g. `1.5kB`
+    pub fn display_si(&self) -> String {
+        const UNITS: [&str; 7] = ["B", "kB", "MB", "GB", "TB", "PB", "EB"];
+        let mut size = self.bytes as f64;
+        let mut unit = 0;
+        while size >= 1000.0 && unit < UNITS.len() - 1 {
+            size /= 1000.0;
+            unit += 1;
+        }
+        format!("{:.1}{}", size, UNITS[unit])
+    }
+}
+
+impl PartialEq for SizeValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.bytes == other.bytes
+    }
+}
+
+impl PartialOrd for SizeValue {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.bytes.partial_cmp(&other.bytes)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuditFinding {
+    /// An artifact directory larger than 1GiB
+    LargeArtifactDir { path: path::PathBuf, size: u64 },
+    /// Artifacts that haven't been modified in at least 30 days
+    StaleArtifacts { days: u32 },
+    /// The project type uses a lockfile but none was found
+    MissingLockfile,
+    /// The dependency directory holds more than 1000 packages
+    OversizedDependencies { count: usize },
+}
+
+impl AuditFinding {
+    pub fn severity(&self) -> Severity {
+        match self {
+            AuditFinding::LargeArtifactDir { .. } => Severity::High,
+            AuditFinding::StaleArtifacts { .. } => Severity::Medium,
+            AuditFinding::MissingLockfile => Severity::Low,
+            AuditFinding::OversizedDependencies { .. } => Severity::Medium,
+        }
+    }
+}
+
 impl Project {
+    fn new(project_type: ProjectType, path: path::PathBuf) -> Project {
+        Project {
+            project_type,
+            path,
+            pre_computed_size: None,
+            artifact_dir_hash: None,
+            last_cleaned: None,
+        }
+    }
+
+    /// Detects the project rooted at `path`, without looking at any subdirectories
+    pub fn detect_in(path: &path::Path) -> Option<Project> {
+        let project_type = detect_project_type_in(fs::read_dir(path).ok()?, CASE_INSENSITIVE_FS)?;
+        Some(Project::new(project_type, path.to_path_buf()))
+    }
+
     pub fn artifact_dirs(&self) -> &[&str] {
         match self.project_type {
-            ProjectType::Cargo => &PROJECT_CARGO_DIRS,
+            ProjectType::Cargo | ProjectType::CargoWorkspace => &PROJECT_CARGO_DIRS,
             ProjectType::Node => &PROJECT_NODE_DIRS,
             ProjectType::Unity => &PROJECT_UNITY_DIRS,
             ProjectType::Stack => &PROJECT_STACK_DIRS,
@@ -104,13 +619,110 @@ impl Project {
             ProjectType::Python => &PROJECT_PYTHON_DIRS,
             ProjectType::CMake => &PROJECT_CMAKE_DIRS,
             ProjectType::Composer => &PROJECT_COMPOSER_DIRS,
+            ProjectType::Buck => &PROJECT_BUCK_DIRS,
+            ProjectType::Pants => &PROJECT_PANTS_DIRS,
+            ProjectType::Poetry => &PROJECT_POETRY_DIRS,
+            ProjectType::PDM => &PROJECT_PDM_DIRS,
+            ProjectType::Hatch => &PROJECT_HATCH_DIRS,
+            ProjectType::Nix => &PROJECT_NIX_DIRS,
+            ProjectType::Rye => &PROJECT_RYE_DIRS,
+            ProjectType::Nx => &PROJECT_NX_DIRS,
+            ProjectType::Turborepo => &PROJECT_TURBOREPO_DIRS,
         }
     }
 
+    /// `artifact_dirs`, keeping only names that stay inside the project directory: relative
+    /// paths made of plain components, with no `..`, root or drive prefix, and no nul bytes.
+    /// Nested dirs such as SBT's `project/target` are kept.
+    pub fn artifact_dirs_verified(&self) -> Vec<&str> {
+        self.artifact_dirs()
+            .iter()
+            .copied()
+            .filter(|ad| is_contained_artifact_dir(ad))
+            .collect()
+    }
+
     pub fn name(&self) -> String {
         self.path.to_str().unwrap().to_string()
     }
 
+    /// Records the length and mtime of every file in the artifact dirs, without reading their
+    /// contents, to compare against after a rebuild with `diff_artifact_snapshot`
+    pub fn snapshot_artifact_hash(&self) -> ArtifactSnapshot {
+        let mut snapshot = ArtifactSnapshot::default();
+        for ad in self.existing_artifact_dirs() {
+            for entry in jwalk::WalkDir::new(ad)
+                .follow_links(SYMLINK_FOLLOW)
+                .parallelism(Parallelism::RayonDefaultPool)
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file())
+            {
+                let path = entry.path();
+                let relative = match path.strip_prefix(&self.path) {
+                    Ok(relative) if !relative.to_string_lossy().contains('\n') => relative,
+                    _ => continue,
+                };
+                let metadata = match entry.metadata() {
+                    Ok(metadata) => metadata,
+                    Err(_) => continue,
+                };
+                let mtime = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|m| m.duration_since(time::UNIX_EPOCH).ok())
+                    .unwrap_or_default();
+                snapshot
+                    .files
+                    .insert(relative.to_path_buf(), (metadata.len(), mtime));
+            }
+        }
+        snapshot
+    }
+
+    /// Compares the artifact dirs as they are now against `snapshot`
+    pub fn diff_artifact_snapshot(&self, snapshot: &ArtifactSnapshot) -> ArtifactDiff {
+        let current = self.snapshot_artifact_hash();
+        let mut diff = ArtifactDiff::default();
+        for (path, entry) in &current.files {
+            match snapshot.files.get(path) {
+                None => diff.added.push(path.clone()),
+                Some(old) if old != entry => diff.modified.push(path.clone()),
+                Some(_) => {}
+            }
+        }
+        diff.removed = snapshot
+            .files
+            .keys()
+            .filter(|path| !current.files.contains_key(*path))
+            .cloned()
+            .collect();
+        diff
+    }
+
+    /// Hashes the listing of every artifact dir, each entry's relative path, length and mtime,
+    /// without reading file contents. Equal hashes from two scans mean the artifacts very
+    /// likely haven't changed. The value is only comparable within one build of kondo.
+    pub fn compute_artifact_hash(&self) -> u64 {
+        use std::hash::{Hash, Hasher};
+
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        for ad in self.existing_artifact_dirs() {
+            for entry in walkdir::WalkDir::new(&ad)
+                .sort_by_file_name()
+                .into_iter()
+                .filter_map(|e| e.ok())
+            {
+                entry.path().strip_prefix(&self.path).ok().hash(&mut hasher);
+                if let Ok(metadata) = entry.metadata() {
+                    metadata.len().hash(&mut hasher);
+                    metadata.modified().ok().hash(&mut hasher);
+                }
+            }
+        }
+        hasher.finish()
+    }
+
     pub fn size(&self) -> u64 {
         self.artifact_dirs()
             .iter()
@@ -119,6 +731,77 @@ impl Project {
             .sum()
     }
 
+    /// Like `size`, but leaves out directories named in `exclude_subdirs` at any depth,
+    /// e.g. `PackageCache` to measure Unity's `Library` without it
+    pub fn size_excluding(&self, exclude_subdirs: &[&str]) -> u64 {
+        self.artifact_dirs()
+            .iter()
+            .copied()
+            .map(|p| {
+                let exclude: Vec<String> = exclude_subdirs.iter().map(|d| d.to_string()).collect();
+                jwalk::WalkDir::new(self.path.join(p))
+                    .follow_links(SYMLINK_FOLLOW)
+                    .process_read_dir(move |_, _, _, children| {
+                        children.retain(|child| match child {
+                            Ok(child) if child.file_type.is_dir() => !exclude
+                                .iter()
+                                .any(|d| child.file_name.to_str() == Some(d.as_str())),
+                            _ => true,
+                        })
+                    })
+                    .parallelism(Parallelism::RayonDefaultPool)
+                    .into_iter()
+                    .filter_map(|e| e.ok())
+                    .filter(|e| e.file_type().is_file())
+                    .filter_map(|e| e.metadata().ok())
+                    .map(|e| e.len())
+                    .sum::<u64>()
+            })
+            .sum()
+    }
+
+    pub fn size_with_units(&self) -> SizeValue {
+        SizeValue::new(self.size())
+    }
+
+    /// Runs `operation` and returns how much the artifact size changed, negative when space
+    /// was freed
+    pub fn size_diff<F: FnOnce()>(&self, operation: F) -> i64 {
+        let before = self.size();
+        operation();
+        self.size() as i64 - before as i64
+    }
+
+    /// Like `size_diff` for an async operation. The measurements themselves block, so run
+    /// this somewhere that's allowed to, e.g. a blocking task.
+    pub async fn size_diff_async<F: std::future::Future<Output = ()>>(&self, operation: F) -> i64 {
+        let before = self.size();
+        operation.await;
+        self.size() as i64 - before as i64
+    }
+
+    /// Like `size`, but measures every artifact directory concurrently. Each directory is
+    /// walked serially on its own rayon task, since a jwalk walk on the shared pool from inside
+    /// a rayon task can deadlock.
+    pub fn size_parallel(&self) -> u64 {
+        self.artifact_dirs()
+            .par_iter()
+            .map(|p| dir_size_with(&self.path.join(p), Parallelism::Serial))
+            .sum()
+    }
+
+    /// Like `size`, but gives up and returns `None` after `timeout`. The measurement keeps
+    /// running on its background thread until it finishes, its result is just discarded.
+    pub fn size_timeout(&self, timeout: time::Duration) -> Option<u64> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let project = self.clone();
+        std::thread::spawn(move || {
+            // the receiver is gone if we already timed out
+            let _ = sender.send(project.size());
+        });
+        receiver.recv_timeout(timeout).ok()
+    }
+
     pub fn size_dirs(&self) -> ProjectSize {
         let mut artifact_size = 0;
         let mut non_artifact_size = 0;
@@ -171,9 +854,147 @@ impl Project {
         }
     }
 
+    /// Sizes of the artifact dirs that exist, keyed by their name in `artifact_dirs`. Only
+    /// artifact dirs are measured, unlike `size_dirs`.
+    pub fn artifact_dirs_sizes(&self) -> HashMap<String, u64> {
+        self.artifact_dirs()
+            .iter()
+            .copied()
+            .filter(|ad| self.path.join(ad).symlink_metadata().is_ok())
+            .map(|ad| (ad.to_string(), dir_size(&self.path.join(ad))))
+            .collect()
+    }
+
+    fn lockfiles(&self) -> &[&str] {
+        match self.project_type {
+            ProjectType::Cargo | ProjectType::CargoWorkspace => &LOCKFILES_CARGO,
+            ProjectType::Node => &LOCKFILES_NODE,
+            ProjectType::Composer => &LOCKFILES_COMPOSER,
+            _ => &[],
+        }
+    }
+
+    fn dependency_dir(&self) -> Option<&str> {
+        match self.project_type {
+            ProjectType::Node => Some(PROJECT_NODE_DIRS[0]),
+            ProjectType::Composer => Some(PROJECT_COMPOSER_DIRS[0]),
+            _ => None,
+        }
+    }
+
+    /// Finds the project `start` is in, checking `start` then each of its parents
+    pub fn detect_in_ancestors(start: &path::Path) -> Option<Project> {
+        start.ancestors().find_map(Project::detect_in)
+    }
+
+    /// Every project `start` is in, deepest first, e.g. a workspace member then its workspace
+    pub fn detect_all_ancestors(start: &path::Path) -> Vec<Project> {
+        start.ancestors().filter_map(Project::detect_in).collect()
+    }
+
+    fn sibling_dirs(&self) -> Vec<path::PathBuf> {
+        let parent = match self.path.parent() {
+            None => return Vec::new(),
+            Some(parent) => parent,
+        };
+        let rd = match fs::read_dir(parent) {
+            Err(_) => return Vec::new(),
+            Ok(rd) => rd,
+        };
+        rd.filter_map(|rd| rd.ok())
+            .filter(|de| de.file_type().map(|ft| ft.is_dir()).unwrap_or(false))
+            .filter(|de| Some(de.file_name().as_os_str()) != self.path.file_name())
+            .map(|de| de.path())
+            .collect()
+    }
+
+    /// Detects projects in the directories next to this one, e.g. the other members of a monorepo
+    pub fn related_projects(&self) -> Vec<Project> {
+        self.sibling_dirs()
+            .into_par_iter()
+            .filter_map(|dir| Project::detect_in(&dir))
+            .collect()
+    }
+
+    pub fn related_project_count(&self) -> usize {
+        self.sibling_dirs()
+            .into_par_iter()
+            .filter_map(|dir| fs::read_dir(dir).ok())
+            .filter_map(|rd| detect_project_type_in(rd, CASE_INSENSITIVE_FS))
+            .count()
+    }
+
+    /// Whether this is a Cargo project inside a workspace, judged by any ancestor directory
+    /// having a `Cargo.toml` with a `[workspace]` section
+    pub fn is_cargo_workspace_member(&self) -> bool {
+        matches!(self.project_type, ProjectType::Cargo)
+            && self.path.ancestors().skip(1).any(is_cargo_workspace_root)
+    }
+
+    /// Whether this project's directory is somewhere below `other`'s
+    pub fn is_nested_inside(&self, other: &Project) -> bool {
+        self.path != other.path && self.path.starts_with(&other.path)
+    }
+
+    /// Checks the project directory for common problems
+    pub fn audit(&self) -> Vec<AuditFinding> {
+        let mut findings = Vec::new();
+
+        let mut newest: Option<time::SystemTime> = None;
+        for path in self
+            .artifact_dirs()
+            .iter()
+            .copied()
+            .map(|ad| self.path.join(ad))
+            .filter(|ad| ad.exists())
+        {
+            let size = dir_size(&path);
+            if size > AUDIT_LARGE_ARTIFACT_SIZE {
+                findings.push(AuditFinding::LargeArtifactDir {
+                    path: path.clone(),
+                    size,
+                });
+            }
+            if let Some(age) = dir_age(&path) {
+                newest = Some(newest.map_or(age, |n| n.max(age)));
+            }
+        }
+
+        if let Some(newest) = newest {
+            let days = time::SystemTime::now()
+                .duration_since(newest)
+                .map(|d| (d.as_secs() / 86_400) as u32)
+                .unwrap_or(0);
+            if days >= AUDIT_STALE_DAYS {
+                findings.push(AuditFinding::StaleArtifacts { days });
+            }
+        }
+
+        // a workspace member shares the Cargo.lock at the workspace root
+        let lockfiles = self.lockfiles();
+        if !lockfiles.is_empty()
+            && !self.is_cargo_workspace_member()
+            && !lockfiles.iter().any(|l| self.path.join(l).exists())
+        {
+            findings.push(AuditFinding::MissingLockfile);
+        }
+
+        if let Some(dep_dir) = self.dependency_dir() {
+            if let Ok(rd) = fs::read_dir(self.path.join(dep_dir)) {
+                let count = rd.filter_map(|e| e.ok()).count();
+                if count > AUDIT_DEPENDENCY_COUNT {
+                    findings.push(AuditFinding::OversizedDependencies { count });
+                }
+            }
+        }
+
+        findings
+    }
+
     pub fn type_name(&self) -> &'static str {
         match self.project_type {
             ProjectType::Cargo => PROJECT_CARGO_NAME,
+            ProjectType::CargoWorkspace => PROJECT_CARGO_WORKSPACE_NAME,
             ProjectType::Node => PROJECT_NODE_NAME,
             ProjectType::Unity => PROJECT_UNITY_NAME,
             ProjectType::Stack => PROJECT_STACK_NAME,
@@ -184,51 +1005,849 @@ impl Project {
             ProjectType::Python => PROJECT_PYTHON_NAME,
             ProjectType::CMake => PROJECT_CMAKE_NAME,
             ProjectType::Composer => PROJECT_COMPOSER_NAME,
+            ProjectType::Buck => PROJECT_BUCK_NAME,
+            ProjectType::Pants => PROJECT_PANTS_NAME,
+            ProjectType::Poetry => PROJECT_POETRY_NAME,
+            ProjectType::PDM => PROJECT_PDM_NAME,
+            ProjectType::Hatch => PROJECT_HATCH_NAME,
+            ProjectType::Nix => PROJECT_NIX_NAME,
+            ProjectType::Rye => PROJECT_RYE_NAME,
+            ProjectType::Nx => PROJECT_NX_NAME,
+            ProjectType::Turborepo => PROJECT_TURBOREPO_NAME,
         }
     }
 
-    /// Deletes the project's artifact directories and their contents
-    pub fn clean(&self) {
-        for artifact_dir in self
-            .artifact_dirs()
-            .iter()
-            .copied()
-            .map(|ad| self.path.join(ad))
-            .filter(|ad| ad.exists())
-        {
-            if let Err(e) = fs::remove_dir_all(&artifact_dir) {
-                eprintln!("error removing directory {:?}: {:?}", artifact_dir, e);
-            }
+    /// An emoji for the project type, for making project lists easier to scan
+    pub fn type_icon(&self) -> &'static str {
+        match self.project_type {
+            ProjectType::Cargo | ProjectType::CargoWorkspace => "🦀",
+            ProjectType::Node => "📦",
+            ProjectType::Unity | ProjectType::Unreal => "🎮",
+            ProjectType::Stack => "λ",
+            ProjectType::SBT | ProjectType::Maven => "☕",
+            ProjectType::CMake => "🔨",
+            ProjectType::Jupyter => "📓",
+            ProjectType::Python
+            | ProjectType::Poetry
+            | ProjectType::PDM
+            | ProjectType::Hatch
+            | ProjectType::Rye => "🐍",
+            ProjectType::Composer => "🐘",
+            ProjectType::Buck => "🦌",
+            ProjectType::Pants => "👖",
+            ProjectType::Nix => "❄",
+            ProjectType::Nx => "🧩",
+            ProjectType::Turborepo => "⚡",
         }
     }
-}
 
-fn is_hidden(entry: &jwalk::DirEntry<((), ())>) -> bool {
-    entry
-        .file_name()
-        .to_str()
-        .map(|s| s.starts_with('.'))
-        .unwrap_or(false)
+    /// Like `type_icon`, but plain ASCII for terminals without Unicode support
+    pub fn type_icon_ascii(&self) -> &'static str {
+        match self.project_type {
+            ProjectType::Cargo | ProjectType::CargoWorkspace => "[rs]",
+            ProjectType::Node => "[js]",
+            ProjectType::Unity | ProjectType::Unreal => "[gm]",
+            ProjectType::Stack => "[hs]",
+            ProjectType::SBT => "[sc]",
+            ProjectType::Maven => "[jv]",
+            ProjectType::CMake => "[cm]",
+            ProjectType::Jupyter => "[nb]",
+            ProjectType::Python
+            | ProjectType::Poetry
+            | ProjectType::PDM
+            | ProjectType::Hatch
+            | ProjectType::Rye => "[py]",
+            ProjectType::Composer => "[php]",
+            ProjectType::Buck => "[bk]",
+            ProjectType::Pants => "[pt]",
+            ProjectType::Nix => "[nix]",
+            ProjectType::Nx => "[nx]",
+            ProjectType::Turborepo => "[tr]",
+        }
+    }
+
+    pub fn clean_preview(&self) -> CleanPreview {
+        let dirs = self
+            .artifact_dirs()
+            .iter()
+            .copied()
+            .map(|ad| {
+                let path = self.path.join(ad);
+                let exists = path.exists();
+                let (size, file_count) = if exists {
+                    dir_size_and_count(&path)
+                } else {
+                    (0, 0)
+                };
+                ArtifactDirPreview {
+                    path,
+                    size,
+                    file_count,
+                    exists,
+                }
+            })
+            .collect();
+        CleanPreview { dirs }
+    }
+
+    fn project_dir_name(&self) -> &std::ffi::OsStr {
+        self.path
+            .file_name()
+            .unwrap_or_else(|| self.path.as_os_str())
+    }
+
+    /// Moves each artifact directory to `destination/<project dir name>/<artifact dir>`,
+    /// copying then deleting when `destination` is on another filesystem
+    pub fn move_artifacts(&self, destination: &path::Path) -> Result<MoveResult, Vec<MoveError>> {
+        let target = destination.join(self.project_dir_name());
+        let mut moved_paths = Vec::new();
+        let mut errors = Vec::new();
+        for ad in self.artifact_dirs() {
+            let from = self.path.join(ad);
+            if !is_existing_artifact(&from) {
+                continue;
+            }
+            let to = target.join(ad);
+            match move_dir(&from, &to) {
+                Ok(()) => moved_paths.push((from, to)),
+                Err(error) => errors.push(MoveError { from, to, error }),
+            }
+        }
+        if errors.is_empty() {
+            Ok(MoveResult { moved_paths })
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Moves artifact directories previously moved with `move_artifacts(source)` back into the project
+    pub fn restore_artifacts(&self, source: &path::Path) -> Result<(), Vec<MoveError>> {
+        let origin = source.join(self.project_dir_name());
+        let errors: Vec<MoveError> = self
+            .artifact_dirs()
+            .iter()
+            .map(|ad| (origin.join(ad), self.path.join(ad)))
+            .filter(|(from, _)| from.symlink_metadata().is_ok())
+            .filter_map(|(from, to)| {
+                move_dir(&from, &to)
+                    .err()
+                    .map(|error| MoveError { from, to, error })
+            })
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Links each artifact dir to `target_base/<artifact dir>`, for every one that exists
+    /// there. Pair with `move_artifacts` so build tools still find their outputs, passing
+    /// `destination/<project dir name>` as `target_base`.
+    pub fn symlink_artifact_dirs(
+        &self,
+        target_base: &path::Path,
+    ) -> io::Result<Vec<path::PathBuf>> {
+        self.symlink_artifact_dirs_with_options(target_base, &SymlinkOptions::default())
+    }
+
+    pub fn symlink_artifact_dirs_with_options(
+        &self,
+        target_base: &path::Path,
+        options: &SymlinkOptions,
+    ) -> io::Result<Vec<path::PathBuf>> {
+        let mut links = Vec::new();
+        for ad in self.artifact_dirs() {
+            let target = target_base.join(ad);
+            if !target.is_dir() {
+                continue;
+            }
+            let link = self.path.join(ad);
+            if link.symlink_metadata().is_ok() {
+                if !options.overwrite_existing {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AlreadyExists,
+                        format!("{} already exists", link.display()),
+                    ));
+                }
+                remove_artifact_dir(&link)?;
+            }
+            if let Some(parent) = link.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            symlink_dir(&target, &link)?;
+            links.push(link);
+        }
+        Ok(links)
+    }
+
+    fn existing_artifact_dirs(&self) -> Vec<path::PathBuf> {
+        self.artifact_dirs()
+            .iter()
+            .copied()
+            .map(|ad| self.path.join(ad))
+            .filter(|ad| is_existing_artifact(ad))
+            .collect()
+    }
+
+    /// Existing artifact dirs paired with the newest mtime of any file inside them (or of the
+    /// dir itself when it's empty), oldest first
+    pub fn artifact_dirs_mtime_sorted(&self) -> Vec<(path::PathBuf, time::SystemTime)> {
+        let mut dirs: Vec<(path::PathBuf, time::SystemTime)> = self
+            .existing_artifact_dirs()
+            .into_iter()
+            .filter_map(|ad| {
+                let modified =
+                    dir_age(&ad).or_else(|| fs::metadata(&ad).and_then(|m| m.modified()).ok())?;
+                Some((ad, modified))
+            })
+            .collect();
+        dirs.sort_by_key(|&(_, modified)| modified);
+        dirs
+    }
+
+    /// Sets the mtime of every existing artifact dir and everything inside it to `time`, for
+    /// setting up fixtures of a given age. Directories are left alone on Windows, where std
+    /// can't open them for writing.
+    #[cfg(feature = "test-helpers")]
+    pub fn touch_artifact_dirs(&self, time: time::SystemTime) -> Result<(), io::Error> {
+        let times = fs::FileTimes::new().set_modified(time);
+        for ad in self.existing_artifact_dirs() {
+            for entry in walkdir::WalkDir::new(ad) {
+                let entry = entry?;
+                if entry.file_type().is_symlink() || (cfg!(windows) && entry.file_type().is_dir()) {
+                    continue;
+                }
+                fs::File::open(entry.path())?.set_times(times)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Every symlink within the artifact dirs, including artifact dirs that are symlinks
+    fn artifact_symlinks(&self) -> Vec<path::PathBuf> {
+        self.existing_artifact_dirs()
+            .iter()
+            .flat_map(|ad| {
+                // don't walk through a symlinked artifact dir into wherever it points
+                if fs::symlink_metadata(ad)
+                    .map(|m| m.file_type().is_symlink())
+                    .unwrap_or(false)
+                {
+                    return vec![ad.clone()];
+                }
+                jwalk::WalkDir::new(ad)
+                    .follow_links(false)
+                    .parallelism(Parallelism::RayonDefaultPool)
+                    .into_iter()
+                    .filter_map(|e| e.ok())
+                    .filter(|e| e.path_is_symlink())
+                    .map(|e| e.path())
+                    .collect()
+            })
+            .collect()
+    }
+
+    pub fn symlink_count(&self) -> u64 {
+        self.artifact_symlinks().len() as u64
+    }
+
+    /// Symlinks within the artifact dirs whose targets don't exist
+    pub fn broken_symlink_paths(&self) -> Vec<path::PathBuf> {
+        self.artifact_symlinks()
+            .into_iter()
+            .filter(|link| fs::metadata(link).is_err())
+            .collect()
+    }
+
+    /// Each existing artifact dir paired with its symlink target, as stored in the link, or
+    /// `None` if it isn't a symlink
+    pub fn artifact_dirs_symlink_targets(&self) -> Vec<(path::PathBuf, Option<path::PathBuf>)> {
+        self.existing_artifact_dirs()
+            .into_iter()
+            .map(|ad| {
+                let target = fs::read_link(&ad).ok();
+                (ad, target)
+            })
+            .collect()
+    }
+
+    /// Whether any artifact dir is itself a symlink, e.g. Nix's `result`
+    pub fn artifact_dirs_are_symlinks(&self) -> bool {
+        self.existing_artifact_dirs().iter().any(|ad| {
+            fs::symlink_metadata(ad)
+                .map(|m| m.file_type().is_symlink())
+                .unwrap_or(false)
+        })
+    }
+
+    /// Total size of the files in all artifact dirs, keyed by file extension. Files without
+    /// an extension are counted under `""`.
+    pub fn artifact_file_extensions(&self) -> HashMap<String, u64> {
+        let mut extensions = HashMap::new();
+        for ad in self.existing_artifact_dirs() {
+            jwalk::WalkDir::new(ad)
+                .follow_links(SYMLINK_FOLLOW)
+                .parallelism(Parallelism::RayonDefaultPool)
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file())
+                .filter_map(|e| {
+                    let len = e.metadata().ok()?.len();
+                    let extension = e
+                        .path()
+                        .extension()
+                        .map(|ext| ext.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    Some((extension, len))
+                })
+                .for_each(|(extension, len)| *extensions.entry(extension).or_insert(0) += len);
+        }
+        extensions
+    }
+
+    /// `artifact_file_extensions` as `(extension, size)` pairs, largest first
+    pub fn size_breakdown_by_extension(&self) -> Vec<(String, u64)> {
+        let mut breakdown: Vec<(String, u64)> =
+            self.artifact_file_extensions().into_iter().collect();
+        breakdown.sort_by(|(a_ext, a_size), (b_ext, b_size)| {
+            b_size.cmp(a_size).then_with(|| a_ext.cmp(b_ext))
+        });
+        breakdown
+    }
+
+    /// Whether every existing artifact dir can be written to, and so removed
+    pub fn can_clean(&self) -> bool {
+        self.existing_artifact_dirs()
+            .iter()
+            .all(|ad| is_writable(ad))
+    }
+
+    /// Cleans the largest artifact dirs first, see `CleanOrder::LargestFirst`
+    pub fn clean_by_priority(&self) -> Result<CleanResult, Vec<CleanError>> {
+        self.clean_with_options(&CleanOptions {
+            priority_order: CleanOrder::LargestFirst,
+            ..CleanOptions::default()
+        })
+    }
+
+    /// Deletes the project's artifact directories, reporting what was removed. Tools watching
+    /// the project through inotify, FSEvents or `ReadDirectoryChangesW` are told about the
+    /// removals by the OS itself, there is nothing extra to send them.
+    pub fn clean_with_options(
+        &self,
+        options: &CleanOptions,
+    ) -> Result<CleanResult, Vec<CleanError>> {
+        self.clean_reporting(options, |_, _| {})
+    }
+
+    /// Like `clean`, calling `cb` right before and after each artifact dir is removed. Unlike
+    /// a running total, each event is about one directory.
+    pub fn clean_with_callback<F: Fn(&path::Path, CleanEvent)>(
+        &self,
+        cb: F,
+    ) -> Result<CleanResult, Vec<CleanError>> {
+        self.clean_reporting(
+            &CleanOptions {
+                measure_sizes: true,
+                ..CleanOptions::default()
+            },
+            cb,
+        )
+    }
+
+    /// Like `clean`, appending a JSON line to `log_path` for each artifact dir removed, see
+    /// `read_clean_log`. Nothing is removed if the log can't be opened.
+    pub fn clean_with_log(&self, log_path: &path::Path) -> Result<CleanResult, Vec<CleanError>> {
+        use io::Write;
+
+        let log = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(log_path)
+            .map_err(|error| {
+                vec![CleanError::IOError {
+                    path: log_path.to_path_buf(),
+                    error,
+                }]
+            })?;
+        let log_errors = std::cell::RefCell::new(Vec::new());
+        let options = CleanOptions {
+            measure_sizes: true,
+            ..CleanOptions::default()
+        };
+        let result = self.clean_reporting(&options, |artifact_dir, event| {
+            if let CleanEvent::AfterRemove { bytes_freed } = event {
+                let entry = CleanLogEntry {
+                    timestamp: time::SystemTime::now(),
+                    project_path: self.path.clone(),
+                    artifact_dir: artifact_dir.to_path_buf(),
+                    bytes_freed,
+                };
+                if let Err(error) = writeln!(&log, "{}", entry.to_json()) {
+                    log_errors.borrow_mut().push(CleanError::IOError {
+                        path: log_path.to_path_buf(),
+                        error,
+                    });
+                }
+            }
+        });
+        let log_errors = log_errors.into_inner();
+        match result {
+            Ok(result) if log_errors.is_empty() => Ok(result),
+            Ok(_) => Err(log_errors),
+            Err(mut errors) => {
+                errors.extend(log_errors);
+                Err(errors)
+            }
+        }
+    }
+
+    fn clean_reporting<F: Fn(&path::Path, CleanEvent)>(
+        &self,
+        options: &CleanOptions,
+        cb: F,
+    ) -> Result<CleanResult, Vec<CleanError>> {
+        let artifact_dirs = self.existing_artifact_dirs();
+
+        if options.verify_permissions {
+            let denied: Vec<CleanError> = artifact_dirs
+                .iter()
+                .filter(|ad| !is_writable(ad))
+                .cloned()
+                .map(CleanError::PermissionDenied)
+                .collect();
+            if !denied.is_empty() {
+                return Err(denied);
+            }
+        }
+
+        let mut result = CleanResult::default();
+        let mut errors = Vec::new();
+        if options.remove_broken_symlinks {
+            for link in self.broken_symlink_paths() {
+                match fs::remove_file(&link) {
+                    Ok(()) => result.removed.push(link),
+                    Err(error) => errors.push(CleanError::IOError { path: link, error }),
+                }
+            }
+        }
+        // measuring walks every artifact dir, so skip it unless something uses the sizes
+        let measure_sizes = options.measure_sizes
+            || matches!(
+                options.priority_order,
+                CleanOrder::LargestFirst | CleanOrder::SmallestFirst
+            );
+        let mut artifact_dirs: Vec<(path::PathBuf, u64)> = artifact_dirs
+            .into_iter()
+            .map(|ad| {
+                let size = if measure_sizes { dir_size(&ad) } else { 0 };
+                (ad, size)
+            })
+            .collect();
+        match options.priority_order {
+            CleanOrder::LargestFirst => {
+                artifact_dirs.sort_by_key(|&(_, size)| std::cmp::Reverse(size))
+            }
+            CleanOrder::SmallestFirst => artifact_dirs.sort_by_key(|&(_, size)| size),
+            CleanOrder::Alphabetical => artifact_dirs.sort(),
+            CleanOrder::Default => {}
+        }
+        for (artifact_dir, size) in artifact_dirs {
+            if artifact_dir.symlink_metadata().is_err() {
+                // a broken symlink removed above
+                continue;
+            }
+            cb(&artifact_dir, CleanEvent::BeforeRemove { size });
+            match remove_artifact_dir(&artifact_dir) {
+                Ok(()) => {
+                    cb(&artifact_dir, CleanEvent::AfterRemove { bytes_freed: size });
+                    result.bytes_freed += size;
+                    result.removed.push(artifact_dir);
+                }
+                Err(error) => {
+                    cb(
+                        &artifact_dir,
+                        CleanEvent::RemoveFailed {
+                            error: error.to_string(),
+                        },
+                    );
+                    errors.push(CleanError::IOError {
+                        path: artifact_dir,
+                        error,
+                    })
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(result)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Partially cleans each artifact dir, deleting every file except its `n` most recently
+    /// modified ones. Directories are left in place. Symlinks are never followed and artifact
+    /// dirs that are themselves symlinks are skipped, so nothing outside the project is touched.
+    pub fn clean_keep_n_builds(&self, n: usize) -> Result<CleanResult, Vec<CleanError>> {
+        let mut result = CleanResult::default();
+        let mut errors = Vec::new();
+        for artifact_dir in self.existing_artifact_dirs() {
+            let is_symlink = fs::symlink_metadata(&artifact_dir)
+                .map(|m| m.file_type().is_symlink())
+                .unwrap_or(true);
+            if is_symlink {
+                continue;
+            }
+            let mut files: Vec<(path::PathBuf, u64, time::SystemTime)> =
+                jwalk::WalkDir::new(&artifact_dir)
+                    .follow_links(false)
+                    .parallelism(Parallelism::RayonDefaultPool)
+                    .into_iter()
+                    .filter_map(|e| e.ok())
+                    .filter(|e| e.file_type().is_file())
+                    .filter_map(|e| {
+                        let metadata = e.metadata().ok()?;
+                        Some((e.path(), metadata.len(), metadata.modified().ok()?))
+                    })
+                    .collect();
+            files.sort_by_key(|&(_, _, modified)| std::cmp::Reverse(modified));
+
+            for (path, len, _) in files.into_iter().skip(n) {
+                match fs::remove_file(&path) {
+                    Ok(()) => {
+                        result.bytes_freed += len;
+                        result.removed.push(path);
+                    }
+                    Err(error) => errors.push(CleanError::IOError { path, error }),
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(result)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Cargo's incremental compilation caches under `target` that exist, empty for other
+    /// project types
+    pub fn incremental_dirs(&self) -> Vec<path::PathBuf> {
+        match self.project_type {
+            ProjectType::Cargo | ProjectType::CargoWorkspace => CARGO_INCREMENTAL_DIRS
+                .iter()
+                .map(|d| self.path.join(d))
+                .filter(|d| d.is_dir())
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Removes only the `incremental_dirs`, freeing their space while keeping the rest of
+    /// `target` so the next build doesn't start from scratch
+    pub fn clean_incremental_only(&self) -> Result<CleanResult, Vec<CleanError>> {
+        let mut result = CleanResult::default();
+        let mut errors = Vec::new();
+        for dir in self.incremental_dirs() {
+            let size = dir_size(&dir);
+            match fs::remove_dir_all(&dir) {
+                Ok(()) => {
+                    result.bytes_freed += size;
+                    result.removed.push(dir);
+                }
+                Err(error) => errors.push(CleanError::IOError { path: dir, error }),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(result)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Writes `last_cleaned` to a `.kondo_meta` file in the project directory, as whole
+    /// seconds since the Unix epoch. Cleaning doesn't do this by itself.
+    pub fn save_metadata(&self) -> io::Result<()> {
+        let mut contents = String::new();
+        if let Some(last_cleaned) = self.last_cleaned {
+            let secs = last_cleaned
+                .duration_since(time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            contents.push_str(&format!("{} = {}\n", KONDO_META_LAST_CLEANED, secs));
+        }
+        fs::write(self.path.join(FILE_KONDO_META), contents)
+    }
+
+    /// Reads `last_cleaned` back from the `.kondo_meta` written by `save_metadata`
+    pub fn load_metadata(&mut self) -> io::Result<()> {
+        let contents = fs::read_to_string(self.path.join(FILE_KONDO_META))?;
+        self.last_cleaned = contents.lines().find_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            if key.trim() != KONDO_META_LAST_CLEANED {
+                return None;
+            }
+            let secs = value.trim().parse().ok()?;
+            Some(time::UNIX_EPOCH + time::Duration::from_secs(secs))
+        });
+        Ok(())
+    }
+
+    /// Deletes the project's artifact directories and their contents
+    pub fn clean(&self) {
+        for artifact_dir in self
+            .artifact_dirs()
+            .iter()
+            .copied()
+            .map(|ad| self.path.join(ad))
+            .filter(|ad| is_existing_artifact(ad))
+        {
+            if let Err(e) = remove_artifact_dir(&artifact_dir) {
+                eprintln!("error removing directory {:?}: {:?}", artifact_dir, e);
+            }
+        }
+    }
+}
+
+fn is_contained_artifact_dir(artifact_dir: &str) -> bool {
+    !artifact_dir.is_empty()
+        && !artifact_dir.contains('\0')
+        && path::Path::new(artifact_dir)
+            .components()
+            .all(|c| matches!(c, path::Component::Normal(_)))
+}
+
+/// Whether `path` is there to be cleaned: a directory, a symlink, or one of the artifacts that
+/// are plain files. A regular file that shares an artifact dir's name, like a `dist` script,
+/// is not an artifact.
+fn is_existing_artifact(path: &path::Path) -> bool {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) => !metadata.file_type().is_file() || is_file_artifact(path),
+        Err(_) => false,
+    }
+}
+
+fn is_file_artifact(path: &path::Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| PROJECT_PDM_FILES.contains(&name))
+}
+
+/// Removes an artifact directory, or just the link if it's a symlink to somewhere else
+/// (e.g. Buck2's `buck-out` pointing into a shared cache, or Nix's `result` into the store).
+/// Plain files are only removed if they're declared as artifacts, like PDM's `.pdm-python`.
+fn remove_artifact_dir(path: &path::Path) -> io::Result<()> {
+    let file_type = fs::symlink_metadata(path)?.file_type();
+    #[cfg(windows)]
+    {
+        // directory symlinks and junctions on windows are removed like empty directories
+        use std::os::windows::fs::FileTypeExt;
+        if file_type.is_symlink_dir() {
+            return fs::remove_dir(path);
+        }
+    }
+    if file_type.is_symlink() || (file_type.is_file() && is_file_artifact(path)) {
+        fs::remove_file(path)
+    } else {
+        fs::remove_dir_all(path)
+    }
+}
+
+#[cfg(unix)]
+fn is_writable(path: &path::Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = match std::ffi::CString::new(path.as_os_str().as_bytes()) {
+        Err(_) => return false,
+        Ok(path) => path,
+    };
+    unsafe { libc::access(path.as_ptr(), libc::W_OK) == 0 }
+}
+
+#[cfg(not(unix))]
+fn is_writable(path: &path::Path) -> bool {
+    fs::metadata(path)
+        .map(|m| !m.permissions().readonly())
+        .unwrap_or(false)
+}
+
+fn move_dir(from: &path::Path, to: &path::Path) -> io::Result<()> {
+    if to.symlink_metadata().is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "destination already exists",
+        ));
+    }
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // rename only works within a filesystem, fall back to copying across them
+    match fs::rename(from, to) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {}
+        result => return result,
+    }
+    if let Err(e) = copy_dir_all(from, to) {
+        // don't leave a partial copy behind, `from` is still intact
+        let _ = remove_artifact_dir(to);
+        return Err(e);
+    }
+    remove_artifact_dir(from)
+}
+
+#[cfg(unix)]
+fn symlink_dir(target: &path::Path, link: &path::Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink_dir(target: &path::Path, link: &path::Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_dir(target, link)
+}
+
+fn copy_dir_all(from: &path::Path, to: &path::Path) -> io::Result<()> {
+    let file_type = fs::symlink_metadata(from)?.file_type();
+    if file_type.is_symlink() {
+        #[cfg(unix)]
+        return std::os::unix::fs::symlink(fs::read_link(from)?, to);
+        #[cfg(not(unix))]
+        return fs::copy(from, to).map(|_| ());
+    }
+    if !file_type.is_dir() {
+        return fs::copy(from, to).map(|_| ());
+    }
+    fs::create_dir(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        copy_dir_all(&entry.path(), &to.join(entry.file_name()))?;
+    }
+    Ok(())
+}
+
+fn is_hidden(entry: &jwalk::DirEntry<((), ())>) -> bool {
+    entry
+        .file_name()
+        .to_str()
+        .map(|s| s.starts_with('.'))
+        .unwrap_or(false)
 }
 
 struct ProjectIter {
     it: walkdir::IntoIter,
     it2: jwalk::DirEntryIter<((), ())>,
+    compute_size: bool,
+    compute_hash: bool,
+    load_metadata: bool,
+    case_insensitive: bool,
+    /// A project detected in the dir last reported by `next_event`, waiting to be yielded
+    found: Option<Project>,
+    detector: Option<(Box<Detector>, DetectorMode)>,
+}
+
+type Detector = dyn Fn(&str) -> Option<ProjectType> + Send + Sync;
+
+/// How `scan_with_detector` combines a custom detector with the built in detection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetectorMode {
+    /// Only use the custom detector
+    Override,
+    /// Use the custom detector for directories the built in detection doesn't recognise
+    Fallback,
 }
 
+#[derive(Debug)]
 pub enum Red {
     IOError(std::io::Error),
     WalkdirError(jwalk::Error),
+    /// The scan root is on a network filesystem, see `ScanOptions::warn_network_paths`
+    NetworkFilesystem {
+        path: path::PathBuf,
+        fs_type: String,
+    },
 }
 
-impl Iterator for ProjectIter {
-    type Item = Result<Project, Red>;
+impl fmt::Display for Red {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Red::IOError(e) => write!(f, "io error: {}", e),
+            Red::WalkdirError(e) => write!(f, "walk error: {}", e),
+            Red::NetworkFilesystem { path, fs_type } => write!(
+                f,
+                "{} is on a {} network filesystem, scanning it may be very slow",
+                path.display(),
+                fs_type
+            ),
+        }
+    }
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
+impl Error for Red {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Red::IOError(e) => Some(e),
+            Red::WalkdirError(e) => Some(e),
+            Red::NetworkFilesystem { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for Red {
+    fn from(e: io::Error) -> Self {
+        Red::IOError(e)
+    }
+}
+
+impl From<jwalk::Error> for Red {
+    fn from(e: jwalk::Error) -> Self {
+        Red::WalkdirError(e)
+    }
+}
+
+impl From<Red> for io::Error {
+    fn from(e: Red) -> Self {
+        match e {
+            Red::IOError(e) => e,
+            Red::WalkdirError(e) => io::Error::other(e),
+            e @ Red::NetworkFilesystem { .. } => io::Error::other(e.to_string()),
+        }
+    }
+}
+
+impl ProjectIter {
+    fn detect(&self, rd: fs::ReadDir) -> Option<ProjectType> {
+        let (detector, mode) = match &self.detector {
+            None => return detect_project_type_in(rd, self.case_insensitive),
+            Some((detector, mode)) => (detector, mode),
+        };
+        let paths: Vec<path::PathBuf> = rd.filter_map(|de| de.ok()).map(|de| de.path()).collect();
+        let built_in = || {
+            paths
+                .par_iter()
+                .filter_map(|path| detect_project_type(path, self.case_insensitive))
+                .min_by_key(detection_priority)
+        };
+        let custom = || {
+            paths
+                .par_iter()
+                .filter_map(|path| detector(path.file_name()?.to_str()?))
+                .min_by_key(detection_priority)
+        };
+        match mode {
+            DetectorMode::Override => custom(),
+            DetectorMode::Fallback => built_in().or_else(custom),
+        }
+    }
+
+    fn next_event(&mut self) -> Option<ProjectEvent> {
+        if let Some(project) = self.found.take() {
+            return Some(ProjectEvent::ProjectFound(project));
+        }
         loop {
             let entry: jwalk::DirEntry<((), ())> = match self.it2.next() {
                 None => return None,
-                Some(Err(e)) => return Some(Err(Red::WalkdirError(e))),
+                Some(Err(e)) => return Some(ProjectEvent::Error(Red::WalkdirError(e))),
                 Some(Ok(entry)) => entry,
             };
             if !entry.file_type().is_dir() {
@@ -239,31 +1858,24 @@ impl Iterator for ProjectIter {
                 continue;
             }
             let rd = match entry.path().read_dir() {
-                Err(e) => return Some(Err(Red::IOError(e))),
+                Err(e) => return Some(ProjectEvent::Error(Red::IOError(e))),
                 Ok(rd) => rd,
             };
-            return rd
-                .into_iter()
-                .par_bridge()
-                .filter_map(|rd| rd.ok())
-                .filter_map(|de| {
-                    if let Some(name) = de.file_name().to_str() {
-                        return Some((name.to_string(), de.path()));
-                    }
-                    None
-                })
-                .filter_map(|(filename, path)| {
-                    if let Some(ty) = get_project_type(&filename) {
-                        return Some(Ok(Project {
-                            project_type: ty,
-                            path,
-                        }));
-                    }
-                    None
-                })
-                .collect::<Vec<_>>()
-                .into_iter()
-                .next();
+            if let Some(project_type) = self.detect(rd) {
+                let mut project = Project::new(project_type, entry.path());
+                if self.compute_size {
+                    project.pre_computed_size = Some(project.size());
+                }
+                if self.compute_hash {
+                    project.artifact_dir_hash = Some(project.compute_artifact_hash());
+                }
+                if self.load_metadata {
+                    // a missing or unreadable .kondo_meta just leaves last_cleaned unset
+                    let _ = project.load_metadata();
+                }
+                self.found = Some(project);
+            }
+            return Some(ProjectEvent::DirectoryEntered(entry.path()));
 
             // intentionally ignoring errors while iterating the ReadDir
             // can't return them because we'll lose the context of where we are
@@ -284,31 +1896,460 @@ impl Iterator for ProjectIter {
     }
 }
 
-fn get_project_type(file_name: &str) -> Option<ProjectType> {
-    match file_name {
-        FILE_CARGO_TOML => Some(ProjectType::Cargo),
-        FILE_PACKAGE_JSON => Some(ProjectType::Node),
-        FILE_ASSEMBLY_CSHARP => Some(ProjectType::Unity),
-        FILE_STACK_HASKELL => Some(ProjectType::Stack),
-        FILE_SBT_BUILD => Some(ProjectType::SBT),
-        FILE_MVN_BUILD => Some(ProjectType::Maven),
-        FILE_CMAKE_BUILD => Some(ProjectType::CMake),
-        FILE_COMPOSER_JSON => Some(ProjectType::Composer),
-        file_name if file_name.ends_with(FILE_UNREAL_SUFFIX) => Some(ProjectType::Unreal),
-        file_name if file_name.ends_with(FILE_JUPYTER_SUFFIX) => Some(ProjectType::Jupyter),
-        file_name if file_name.ends_with(FILE_PYTHON_SUFFIX) => Some(ProjectType::Python),
-        _ => None,
+impl Iterator for ProjectIter {
+    type Item = Result<Project, Red>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.next_event()? {
+                ProjectEvent::ProjectFound(project) => return Some(Ok(project)),
+                ProjectEvent::Error(e) => return Some(Err(e)),
+                _ => continue,
+            }
+        }
+    }
+}
+
+/// Picks one project type for a directory, even if several of its files match
+fn detect_project_type_in(rd: fs::ReadDir, case_insensitive: bool) -> Option<ProjectType> {
+    rd.par_bridge()
+        .filter_map(|rd| rd.ok())
+        .filter_map(|de| detect_project_type(&de.path(), case_insensitive))
+        .min_by_key(detection_priority)
+}
+
+/// Like `get_project_type`, but can look inside manifests that are shared between tools
+fn detect_project_type(path: &path::Path, case_insensitive: bool) -> Option<ProjectType> {
+    let file_name = path.file_name()?.to_str()?;
+    let file_name = if case_insensitive {
+        canonical_file_name(file_name)
+    } else {
+        Cow::Borrowed(file_name)
+    };
+    match file_name.as_ref() {
+        FILE_PYPROJECT_TOML => get_pyproject_type(path),
+        // a BUCK file inside a Buck cell is just a package of the project rooted at `.buckconfig`
+        FILE_BUCK_BUILD if in_buck_cell(path) => None,
+        file_name => get_project_type(file_name),
+    }
+}
+
+/// Whether a directory above the one containing `path` has a `.buckconfig`
+fn in_buck_cell(path: &path::Path) -> bool {
+    path.ancestors()
+        .skip(2)
+        .any(|dir| dir.join(FILE_BUCK_CONFIG).is_file())
+}
+
+/// Maps a file name onto the spelling `get_project_type` expects, ignoring case
+fn canonical_file_name(file_name: &str) -> Cow<'_, str> {
+    match PROJECT_FILES
+        .iter()
+        .find(|f| f.eq_ignore_ascii_case(file_name))
+    {
+        Some(f) => Cow::Borrowed(f),
+        // the suffixes are all lowercase
+        None => Cow::Owned(file_name.to_lowercase()),
+    }
+}
+
+fn get_pyproject_type(path: &path::Path) -> Option<ProjectType> {
+    let contents = fs::read_to_string(path).ok()?;
+    if contents.contains(PYPROJECT_POETRY_SECTION) {
+        return Some(ProjectType::Poetry);
+    }
+    if contents.contains(PYPROJECT_PDM_SECTION) {
+        return Some(ProjectType::PDM);
+    }
+    if contents.contains(PYPROJECT_RYE_SECTION) {
+        return Some(ProjectType::Rye);
+    }
+    None
+}
+
+/// When a directory matches several project types, the lowest value wins. Generic
+/// suffix matches lose to dedicated manifests, so `main.py` next to a Poetry
+/// `pyproject.toml` is still a Poetry project, and the more specific `hatch.toml`
+/// wins over a `pyproject.toml` sitting beside it. Likewise `nx.json` and `turbo.json` win
+/// over the `package.json` next to them, while `package.json` ranks with every other
+/// manifest.
+fn detection_priority(project_type: &ProjectType) -> u8 {
+    match project_type {
+        ProjectType::Nx | ProjectType::Turborepo => 0,
+        ProjectType::Poetry | ProjectType::PDM | ProjectType::Rye => 2,
+        ProjectType::Python | ProjectType::Jupyter => 3,
+        _ => 1,
+    }
+}
+
+fn get_project_type(file_name: &str) -> Option<ProjectType> {
+    match file_name {
+        FILE_CARGO_TOML => Some(ProjectType::Cargo),
+        FILE_PACKAGE_JSON => Some(ProjectType::Node),
+        FILE_ASSEMBLY_CSHARP => Some(ProjectType::Unity),
+        FILE_STACK_HASKELL => Some(ProjectType::Stack),
+        FILE_SBT_BUILD => Some(ProjectType::SBT),
+        FILE_MVN_BUILD => Some(ProjectType::Maven),
+        FILE_CMAKE_BUILD => Some(ProjectType::CMake),
+        FILE_COMPOSER_JSON => Some(ProjectType::Composer),
+        FILE_BUCK_CONFIG | FILE_BUCK_BUILD => Some(ProjectType::Buck),
+        FILE_PANTS_TOML => Some(ProjectType::Pants),
+        FILE_PDM_LOCK => Some(ProjectType::PDM),
+        FILE_HATCH_TOML => Some(ProjectType::Hatch),
+        FILE_NIX_DEFAULT | FILE_NIX_SHELL | FILE_NIX_FLAKE => Some(ProjectType::Nix),
+        FILE_RYE_LOCK => Some(ProjectType::Rye),
+        FILE_NX_JSON => Some(ProjectType::Nx),
+        FILE_TURBO_JSON => Some(ProjectType::Turborepo),
+        file_name if file_name.ends_with(FILE_UNREAL_SUFFIX) => Some(ProjectType::Unreal),
+        file_name if file_name.ends_with(FILE_JUPYTER_SUFFIX) => Some(ProjectType::Jupyter),
+        file_name if file_name.ends_with(FILE_PYTHON_SUFFIX) => Some(ProjectType::Python),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    /// Only scan directories on these filesystem types (as named in `/proc/mounts`, e.g.
+    /// `ext4` or `btrfs`), skipping things like `tmpfs` or network mounts. Empty allows all.
+    #[cfg(target_os = "linux")]
+    pub filesystem_types: Vec<String>,
+    /// Report Cargo workspaces as a single `CargoWorkspace` project instead of one project per
+    /// member. This has to wait for the whole scan to finish before yielding anything.
+    pub merge_cargo_workspaces: bool,
+    /// Measure each project's artifact size before yielding it, filling in
+    /// `Project::pre_computed_size`
+    pub compute_size_during_scan: bool,
+    /// Hash each project's artifact dir listing while scanning, filling in
+    /// `Project::artifact_dir_hash`
+    pub compute_artifact_hash: bool,
+    /// Read each project's `.kondo_meta`, filling in `Project::last_cleaned`
+    pub load_metadata: bool,
+    /// Match manifest names ignoring case, so `CARGO.TOML` is a Cargo project. Defaults to
+    /// `true` on Windows and macOS, whose filesystems are usually case insensitive.
+    pub case_insensitive: bool,
+    /// Yield a `Red::NetworkFilesystem` error before scanning a root on NFS or SMB, where a
+    /// full walk can take hours. The scan still goes ahead.
+    pub warn_network_paths: bool,
+    /// Directories to leave out of the scan, along with everything below them. These are
+    /// compared with the walked paths as given, so use the same form as the scan root.
+    pub exclude_paths: Vec<path::PathBuf>,
+    /// Skip projects already found through another path, e.g. via a symlink, see
+    /// `deduplicate_projects`
+    pub deduplicate: bool,
+    /// Don't enter directories known to never hold projects, see `BUILTIN_SKIP_DIRS`
+    pub builtin_skip_dirs: bool,
+    /// More directory names not to enter, wherever they appear, on top of the built in ones
+    pub additional_skip_dirs: Vec<String>,
+}
+
+/// Directory names never entered when `ScanOptions::builtin_skip_dirs` is set. The dot
+/// directories are hidden anyway, they're listed in case hidden directories ever get scanned.
+const BUILTIN_SKIP_DIRS: [&str; 4] = [".cache", ".steam", ".mozilla", "AppData"];
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        ScanOptions {
+            #[cfg(target_os = "linux")]
+            filesystem_types: Vec::new(),
+            merge_cargo_workspaces: false,
+            compute_size_during_scan: false,
+            compute_artifact_hash: false,
+            load_metadata: false,
+            case_insensitive: CASE_INSENSITIVE_FS,
+            warn_network_paths: true,
+            exclude_paths: Vec::new(),
+            deduplicate: false,
+            builtin_skip_dirs: true,
+            additional_skip_dirs: Vec::new(),
+        }
+    }
+}
+
+/// Paths to always exclude from `scan`, separated like `PATH` (`:` on Unix, `;` on Windows)
+const ENV_KONDO_EXCLUDE: &str = "KONDO_EXCLUDE";
+
+impl ScanOptions {
+    /// Default options, excluding any paths listed in the `KONDO_EXCLUDE` environment variable
+    pub fn from_env() -> Self {
+        ScanOptions {
+            exclude_paths: std::env::var_os(ENV_KONDO_EXCLUDE)
+                .map(|paths| {
+                    std::env::split_paths(&paths)
+                        .filter(|p| !p.as_os_str().is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            ..ScanOptions::default()
+        }
+    }
+}
+
+#[cfg(feature = "cli")]
+const ARG_FILESYSTEM_TYPE: &str = "filesystem-type";
+#[cfg(feature = "cli")]
+const ARG_MERGE_CARGO_WORKSPACES: &str = "merge-cargo-workspaces";
+#[cfg(feature = "cli")]
+const ARG_COMPUTE_SIZE: &str = "compute-size";
+#[cfg(feature = "cli")]
+const ARG_ARTIFACT_HASH: &str = "artifact-hash";
+#[cfg(feature = "cli")]
+const ARG_LOAD_METADATA: &str = "load-metadata";
+#[cfg(feature = "cli")]
+const ARG_CASE_INSENSITIVE: &str = "case-insensitive";
+#[cfg(feature = "cli")]
+const ARG_CASE_SENSITIVE: &str = "case-sensitive";
+#[cfg(feature = "cli")]
+const ARG_NO_NETWORK_WARNING: &str = "no-network-warning";
+#[cfg(feature = "cli")]
+const ARG_EXCLUDE: &str = "exclude";
+#[cfg(feature = "cli")]
+const ARG_DEDUPLICATE: &str = "deduplicate";
+#[cfg(feature = "cli")]
+const ARG_NO_BUILTIN_SKIP_DIRS: &str = "no-builtin-skip-dirs";
+#[cfg(feature = "cli")]
+const ARG_SKIP_DIR: &str = "skip-dir";
+
+#[cfg(feature = "cli")]
+impl ScanOptions {
+    /// Argument definitions for every option, to add to a `clap::Command` and read back with
+    /// `from_clap_matches`
+    pub fn clap_args() -> Vec<clap::Arg<'static>> {
+        use clap::{Arg, ArgAction};
+
+        let mut args = vec![
+            Arg::new(ARG_MERGE_CARGO_WORKSPACES)
+                .long(ARG_MERGE_CARGO_WORKSPACES)
+                .action(ArgAction::SetTrue)
+                .help("Report each Cargo workspace as a single project"),
+            Arg::new(ARG_COMPUTE_SIZE)
+                .long(ARG_COMPUTE_SIZE)
+                .action(ArgAction::SetTrue)
+                .help("Measure artifact sizes while scanning"),
+            Arg::new(ARG_ARTIFACT_HASH)
+                .long(ARG_ARTIFACT_HASH)
+                .action(ArgAction::SetTrue)
+                .help("Hash artifact dir listings while scanning, to detect changes"),
+            Arg::new(ARG_LOAD_METADATA)
+                .long(ARG_LOAD_METADATA)
+                .action(ArgAction::SetTrue)
+                .help("Read when each project was last cleaned from its .kondo_meta"),
+            Arg::new(ARG_CASE_INSENSITIVE)
+                .long(ARG_CASE_INSENSITIVE)
+                .action(ArgAction::SetTrue)
+                .conflicts_with(ARG_CASE_SENSITIVE)
+                .help("Match manifest file names ignoring case"),
+            Arg::new(ARG_CASE_SENSITIVE)
+                .long(ARG_CASE_SENSITIVE)
+                .action(ArgAction::SetTrue)
+                .help("Match manifest file names exactly"),
+            Arg::new(ARG_NO_NETWORK_WARNING)
+                .long(ARG_NO_NETWORK_WARNING)
+                .action(ArgAction::SetTrue)
+                .help("Don't warn about scanning network filesystems"),
+            Arg::new(ARG_EXCLUDE)
+                .long(ARG_EXCLUDE)
+                .value_name("PATH")
+                .action(ArgAction::Append)
+                .value_parser(clap::value_parser!(path::PathBuf))
+                .help("Skip this directory and everything below it"),
+            Arg::new(ARG_DEDUPLICATE)
+                .long(ARG_DEDUPLICATE)
+                .action(ArgAction::SetTrue)
+                .help("Report projects reachable through several paths only once"),
+            Arg::new(ARG_NO_BUILTIN_SKIP_DIRS)
+                .long(ARG_NO_BUILTIN_SKIP_DIRS)
+                .action(ArgAction::SetTrue)
+                .help("Enter directories like AppData that are skipped by default"),
+            Arg::new(ARG_SKIP_DIR)
+                .long(ARG_SKIP_DIR)
+                .value_name("NAME")
+                .action(ArgAction::Append)
+                .value_parser(clap::value_parser!(String))
+                .help("Never enter directories with this name"),
+        ];
+        if cfg!(target_os = "linux") {
+            args.push(
+                Arg::new(ARG_FILESYSTEM_TYPE)
+                    .long(ARG_FILESYSTEM_TYPE)
+                    .value_name("TYPE")
+                    .action(ArgAction::Append)
+                    .value_parser(clap::value_parser!(String))
+                    .help("Only scan directories on this filesystem type, e.g. ext4"),
+            );
+        }
+        args
+    }
+
+    /// Builds options from matches of a command that includes `clap_args`
+    pub fn from_clap_matches(matches: &clap::ArgMatches) -> Self {
+        let flag = |id| matches.get_one::<bool>(id).copied().unwrap_or(false);
+        let defaults = ScanOptions::default();
+        ScanOptions {
+            #[cfg(target_os = "linux")]
+            filesystem_types: matches
+                .get_many::<String>(ARG_FILESYSTEM_TYPE)
+                .map(|types| types.cloned().collect())
+                .unwrap_or_default(),
+            merge_cargo_workspaces: flag(ARG_MERGE_CARGO_WORKSPACES),
+            compute_size_during_scan: flag(ARG_COMPUTE_SIZE),
+            compute_artifact_hash: flag(ARG_ARTIFACT_HASH),
+            load_metadata: flag(ARG_LOAD_METADATA),
+            case_insensitive: if flag(ARG_CASE_INSENSITIVE) {
+                true
+            } else if flag(ARG_CASE_SENSITIVE) {
+                false
+            } else {
+                defaults.case_insensitive
+            },
+            warn_network_paths: !flag(ARG_NO_NETWORK_WARNING),
+            exclude_paths: matches
+                .get_many::<path::PathBuf>(ARG_EXCLUDE)
+                .map(|paths| paths.cloned().collect())
+                .unwrap_or_default(),
+            deduplicate: flag(ARG_DEDUPLICATE),
+            builtin_skip_dirs: !flag(ARG_NO_BUILTIN_SKIP_DIRS),
+            additional_skip_dirs: matches
+                .get_many::<String>(ARG_SKIP_DIR)
+                .map(|names| names.cloned().collect())
+                .unwrap_or_default(),
+        }
     }
 }
 
 pub fn scan<P: AsRef<path::Path>>(p: &P) -> impl Iterator<Item = Result<Project, Red>> {
+    scan_with_options(p, &ScanOptions::from_env())
+}
+
+pub fn scan_with_options<P: AsRef<path::Path>>(
+    p: &P,
+    options: &ScanOptions,
+) -> impl Iterator<Item = Result<Project, Red>> {
+    scan_with(p, options, None)
+}
+
+/// Like `scan`, but `detector` is given each file name in a directory to recognise project
+/// types kondo doesn't know about, either instead of or after the built in detection
+pub fn scan_with_detector<P, F>(
+    p: &P,
+    detector: F,
+    mode: DetectorMode,
+) -> impl Iterator<Item = Result<Project, Red>>
+where
+    P: AsRef<path::Path>,
+    F: Fn(&str) -> Option<ProjectType> + Send + Sync + 'static,
+{
+    scan_with(
+        p,
+        &ScanOptions::from_env(),
+        Some((Box::new(detector), mode)),
+    )
+}
+
+fn scan_with<P: AsRef<path::Path>>(
+    p: &P,
+    options: &ScanOptions,
+    detector: Option<(Box<Detector>, DetectorMode)>,
+) -> impl Iterator<Item = Result<Project, Red>> {
+    let network_warning = network_warning(p.as_ref(), options).map(Err);
+    let mut projects = project_iter(p, options);
+    projects.detector = detector;
+    let projects = if options.merge_cargo_workspaces {
+        Either::Left(merge_cargo_workspaces(projects.collect()).into_iter())
+    } else {
+        Either::Right(projects)
+    };
+    let projects = if options.deduplicate {
+        let mut seen = std::collections::HashSet::new();
+        Either::Left(projects.filter(move |r| match r {
+            Ok(project) => seen.insert(canonical_project_path(project)),
+            Err(_) => true,
+        }))
+    } else {
+        Either::Right(projects)
+    };
+    network_warning.into_iter().chain(projects)
+}
+
+fn canonical_project_path(project: &Project) -> path::PathBuf {
+    fs::canonicalize(&project.path).unwrap_or_else(|_| project.path.clone())
+}
+
+/// Drops projects whose canonical path was already seen, keeping the first occurrence
+pub fn deduplicate_projects(projects: Vec<Project>) -> Vec<Project> {
+    let mut seen = std::collections::HashSet::new();
+    projects
+        .into_iter()
+        .filter(|project| seen.insert(canonical_project_path(project)))
+        .collect()
+}
+
+/// Combines the results of two scans, e.g. of overlapping roots, dropping duplicates as
+/// `deduplicate_projects` does and sorting by path
+pub fn merge_scan_results(a: Vec<Project>, b: Vec<Project>) -> Vec<Project> {
+    let mut merged = deduplicate_projects(a.into_iter().chain(b).collect());
+    merged.sort_by(|x, y| x.path.cmp(&y.path));
+    merged
+}
+
+/// The projects in `base` that aren't in `exclude`, compared by canonical path
+pub fn subtract_scan_results(base: Vec<Project>, exclude: Vec<Project>) -> Vec<Project> {
+    let exclude: std::collections::HashSet<path::PathBuf> =
+        exclude.iter().map(canonical_project_path).collect();
+    base.into_iter()
+        .filter(|project| !exclude.contains(&canonical_project_path(project)))
+        .collect()
+}
+
+fn network_warning(p: &path::Path, options: &ScanOptions) -> Option<Red> {
+    if !options.warn_network_paths {
+        return None;
+    }
+    network_fs_type(p).map(|fs_type| Red::NetworkFilesystem {
+        path: p.to_path_buf(),
+        fs_type,
+    })
+}
+
+fn project_iter<P: AsRef<path::Path>>(p: &P, options: &ScanOptions) -> ProjectIter {
+    #[cfg(target_os = "linux")]
+    let allowed_devices = allowed_devices(&options.filesystem_types);
+    let exclude_paths = options.exclude_paths.clone();
+    let mut skip_dirs = options.additional_skip_dirs.clone();
+    if options.builtin_skip_dirs {
+        skip_dirs.extend(BUILTIN_SKIP_DIRS.iter().map(|d| d.to_string()));
+    }
+
     let j = jwalk::WalkDir::new(p)
         .follow_links(SYMLINK_FOLLOW)
         .skip_hidden(true)
-        .process_read_dir(|_, _, _, v| {
+        .process_read_dir(move |_, _, _, v| {
+            if !skip_dirs.is_empty() {
+                v.retain(|x| match x {
+                    Ok(x) if x.file_type.is_dir() => !skip_dirs
+                        .iter()
+                        .any(|d| x.file_name.to_str() == Some(d.as_str())),
+                    _ => true,
+                });
+            }
+            if !exclude_paths.is_empty() {
+                v.retain(|x| match x {
+                    Ok(x) if x.file_type.is_dir() => {
+                        let path = x.path();
+                        !exclude_paths.iter().any(|e| path.starts_with(e))
+                    }
+                    _ => true,
+                });
+            }
+            #[cfg(target_os = "linux")]
+            if let Some(devices) = &allowed_devices {
+                v.retain(|x| match x {
+                    Ok(x) if x.file_type.is_dir() => on_allowed_device(&x.path(), devices),
+                    _ => true,
+                });
+            }
             v.par_iter_mut()
                 .filter_map(|x| x.as_mut().ok())
-                .for_each(|mut x| {
+                .for_each(|x| {
                     if !x.file_type.is_dir() {
                         x.read_children_path = None;
                         return;
@@ -327,136 +2368,2815 @@ pub fn scan<P: AsRef<path::Path>>(p: &P) -> impl Iterator<Item = Result<Project,
             .follow_links(SYMLINK_FOLLOW)
             .into_iter(),
         it2: j,
+        compute_size: options.compute_size_during_scan,
+        compute_hash: options.compute_artifact_hash,
+        load_metadata: options.load_metadata,
+        case_insensitive: options.case_insensitive,
+        found: None,
+        detector: None,
     }
 }
 
-pub fn dir_size(path: &path::Path) -> u64 {
-    jwalk::WalkDir::new(path)
-        .follow_links(SYMLINK_FOLLOW)
-        .parallelism(Parallelism::RayonDefaultPool)
+/// Progress of a scan, as yielded by `scan_events`
+#[derive(Debug)]
+pub enum ProjectEvent {
+    ProjectFound(Project),
+    /// A directory was checked for a project, reported before any project found in it
+    DirectoryEntered(path::PathBuf),
+    Error(Red),
+    /// Always the last event
+    ScanComplete {
+        total_dirs: u64,
+        total_projects: u64,
+    },
+}
+
+struct ScanEvents {
+    warning: Option<Red>,
+    projects: ProjectIter,
+    total_dirs: u64,
+    total_projects: u64,
+    complete: bool,
+}
+
+impl Iterator for ScanEvents {
+    type Item = ProjectEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(e) = self.warning.take() {
+            return Some(ProjectEvent::Error(e));
+        }
+        if self.complete {
+            return None;
+        }
+        match self.projects.next_event() {
+            Some(event) => {
+                match event {
+                    ProjectEvent::ProjectFound(_) => self.total_projects += 1,
+                    ProjectEvent::DirectoryEntered(_) => self.total_dirs += 1,
+                    _ => {}
+                }
+                Some(event)
+            }
+            None => {
+                self.complete = true;
+                Some(ProjectEvent::ScanComplete {
+                    total_dirs: self.total_dirs,
+                    total_projects: self.total_projects,
+                })
+            }
+        }
+    }
+}
+
+/// Like `scan`, but also reports each directory checked and a final `ScanComplete` with
+/// totals, for showing progress while a scan runs
+pub fn scan_events<P: AsRef<path::Path>>(p: &P) -> impl Iterator<Item = ProjectEvent> {
+    let options = ScanOptions::from_env();
+    ScanEvents {
+        warning: network_warning(p.as_ref(), &options),
+        projects: project_iter(p, &options),
+        total_dirs: 0,
+        total_projects: 0,
+        complete: false,
+    }
+}
+
+/// Scans for projects whose directory was modified after `since`, such as ones created since
+/// an earlier scan. Errors are always yielded.
+pub fn scan_new_since<P: AsRef<path::Path>>(
+    p: &P,
+    since: time::SystemTime,
+) -> impl Iterator<Item = Result<Project, Red>> {
+    // Older directories can't be skipped during the walk, a directory's mtime only changes
+    // when its direct children do, so a project created deeper down leaves it untouched.
+    scan(p).filter(move |result| match result {
+        Ok(project) => fs::metadata(&project.path)
+            .and_then(|m| m.modified())
+            .map(|modified| modified > since)
+            .unwrap_or(false),
+        Err(_) => true,
+    })
+}
+
+fn merge_cargo_workspaces(results: Vec<Result<Project, Red>>) -> Vec<Result<Project, Red>> {
+    let roots: Vec<path::PathBuf> = results
+        .iter()
+        .filter_map(|r| r.as_ref().ok())
+        .filter(|p| matches!(p.project_type, ProjectType::Cargo))
+        .filter(|p| is_cargo_workspace_root(&p.path))
+        .map(|p| p.path.clone())
+        .collect();
+
+    results
         .into_iter()
-        .par_bridge()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-        .filter_map(|e| e.metadata().ok())
-        .map(|e| e.len())
-        .sum()
+        .filter_map(|r| match r {
+            Ok(mut p) if matches!(p.project_type, ProjectType::Cargo) => {
+                if roots.contains(&p.path) {
+                    p.project_type = ProjectType::CargoWorkspace;
+                } else if roots.iter().any(|root| p.path.starts_with(root))
+                    && p.is_cargo_workspace_member()
+                    // members build into the workspace's target, a crate with its own is
+                    // excluded from the workspace and cleaned separately
+                    && !p.path.join(PROJECT_CARGO_DIRS[0]).exists()
+                {
+                    return None;
+                }
+                Some(Ok(p))
+            }
+            r => Some(r),
+        })
+        .collect()
 }
 
-pub fn pretty_size(size: u64) -> String {
-    const KIBIBYTE: u64 = 1024;
-    const MEBIBYTE: u64 = 1_048_576;
-    const GIBIBYTE: u64 = 1_073_741_824;
-    const TEBIBYTE: u64 = 1_099_511_627_776;
-    const PEBIBYTE: u64 = 1_125_899_906_842_624;
-    const EXBIBYTE: u64 = 1_152_921_504_606_846_976;
+fn is_cargo_workspace_root(dir: &path::Path) -> bool {
+    fs::read_to_string(dir.join(FILE_CARGO_TOML))
+        .map(|manifest| manifest.contains(CARGO_WORKSPACE_SECTION))
+        .unwrap_or(false)
+}
 
-    let (size, symbol) = match size {
-        size if size < KIBIBYTE => (size as f64, "B"),
-        size if size < MEBIBYTE => (size as f64 / KIBIBYTE as f64, "KiB"),
-        size if size < GIBIBYTE => (size as f64 / MEBIBYTE as f64, "MiB"),
-        size if size < TEBIBYTE => (size as f64 / GIBIBYTE as f64, "GiB"),
-        size if size < PEBIBYTE => (size as f64 / TEBIBYTE as f64, "TiB"),
-        size if size < EXBIBYTE => (size as f64 / PEBIBYTE as f64, "PiB"),
-        _ => (size as f64 / EXBIBYTE as f64, "EiB"),
-    };
+#[cfg(target_os = "linux")]
+const PROC_MOUNTS: &str = "/proc/mounts";
 
-    format!("{:.1}{}", size, symbol)
+/// Parses `/proc/mounts` formatted text into `(mount point, filesystem type)` pairs
+#[cfg(target_os = "linux")]
+fn parse_mounts(contents: &str) -> Vec<(path::PathBuf, String)> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let _device = fields.next()?;
+            let mount_point = fields.next()?;
+            let fs_type = fields.next()?;
+            Some((
+                path::PathBuf::from(unescape_mount_field(mount_point)),
+                fs_type.to_string(),
+            ))
+        })
+        .collect()
 }
-#[derive(Debug, Clone)]
-pub struct MultiError<E: Error> {
-    errs: Vec<E>,
-    success: Vec<Project>,
+
+/// The kernel escapes whitespace and backslashes in mount points as octal, e.g. `\040` for a space
+#[cfg(target_os = "linux")]
+fn unescape_mount_field(field: &str) -> String {
+    field
+        .replace("\\040", " ")
+        .replace("\\011", "\t")
+        .replace("\\012", "\n")
+        .replace("\\134", "\\")
 }
 
-impl<E: Error> Error for MultiError<E> {}
+/// Device ids of every mount whose filesystem type is in `filesystem_types`, or `None` if
+/// there is nothing to filter on
+#[cfg(target_os = "linux")]
+fn allowed_devices(filesystem_types: &[String]) -> Option<std::collections::HashSet<u64>> {
+    use std::os::unix::fs::MetadataExt;
 
-impl<E: Error> MultiError<E> {
-    pub fn errs(&self) -> &Vec<E> {
-        &self.errs
+    if filesystem_types.is_empty() {
+        return None;
     }
-    pub fn success(&self) -> &Vec<Project> {
-        &self.success
+    let contents = fs::read_to_string(PROC_MOUNTS).ok()?;
+    Some(
+        parse_mounts(&contents)
+            .into_iter()
+            .filter(|(_, fs_type)| filesystem_types.contains(fs_type))
+            .filter_map(|(mount_point, _)| fs::metadata(mount_point).ok())
+            .map(|m| m.dev())
+            .collect(),
+    )
+}
+
+#[cfg(target_os = "linux")]
+fn on_allowed_device(path: &path::Path, devices: &std::collections::HashSet<u64>) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    fs::metadata(path)
+        .map(|m| devices.contains(&m.dev()))
+        .unwrap_or(true)
+}
+
+/// Filesystem types considered network mounts by `ScanOptions::warn_network_paths`
+const NETWORK_FS_TYPES: [&str; 6] = ["nfs", "nfs4", "cifs", "smbfs", "smb3", "afpfs"];
+
+/// The network filesystem type `path` is on, if any
+#[cfg(target_os = "linux")]
+fn network_fs_type(path: &path::Path) -> Option<String> {
+    let contents = fs::read_to_string(PROC_MOUNTS).ok()?;
+    let path = fs::canonicalize(path).ok()?;
+    network_fs_type_in(&parse_mounts(&contents), &path)
+}
+
+/// The network filesystem type of the innermost mount in `mounts` containing `path`, if any
+#[cfg(target_os = "linux")]
+fn network_fs_type_in(mounts: &[(path::PathBuf, String)], path: &path::Path) -> Option<String> {
+    mounts
+        .iter()
+        .filter(|(mount_point, _)| path.starts_with(mount_point))
+        .max_by_key(|(mount_point, _)| mount_point.components().count())
+        .map(|(_, fs_type)| fs_type)
+        .filter(|fs_type| NETWORK_FS_TYPES.contains(&fs_type.as_str()))
+        .cloned()
+}
+
+#[cfg(target_os = "macos")]
+fn network_fs_type(path: &path::Path) -> Option<String> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
     }
+    let fs_type = unsafe { std::ffi::CStr::from_ptr(stat.f_fstypename.as_ptr()) }
+        .to_string_lossy()
+        .into_owned();
+    Some(fs_type).filter(|fs_type| NETWORK_FS_TYPES.contains(&fs_type.as_str()))
 }
 
-impl<E: Error> FromIterator<Result<Project, E>> for MultiError<E> {
-    fn from_iter<T: IntoIterator<Item = Result<Project, E>>>(iter: T) -> Self {
-        let mut res = MultiError {
-            errs: Vec::new(),
-            success: Vec::new(),
-        };
-        iter.into_iter().for_each(|x: Result<Project, E>| match x {
-            Ok(p) => res.success.push(p),
-            Err(e) => res.errs.push(e),
-        });
-        res
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn network_fs_type(_path: &path::Path) -> Option<String> {
+    None
+}
+
+#[derive(Debug)]
+pub enum ScanJsonError {
+    IOError(io::Error),
+}
+
+impl fmt::Display for ScanJsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScanJsonError::IOError(e) => write!(f, "error writing scan output: {}", e),
+        }
     }
 }
 
-pub fn clean(project_path: &str) -> Result<(), Box<dyn error::Error>> {
-    let project = fs::read_dir(project_path)?
-        .par_bridge()
-        .filter_map(|rd| rd.ok())
-        .find_map_any(|dir_entry| {
-            let file_name = dir_entry.file_name().into_string().ok()?;
-            // let p_type = match file_name.as_str() {
-            //     FILE_CARGO_TOML => Some(ProjectType::Cargo),
-            //     FILE_PACKAGE_JSON => Some(ProjectType::Node),
-            //     FILE_ASSEMBLY_CSHARP => Some(ProjectType::Unity),
-            //     FILE_STACK_HASKELL => Some(ProjectType::Stack),
-            //     FILE_SBT_BUILD => Some(ProjectType::SBT),
-            //     FILE_MVN_BUILD => Some(ProjectType::Maven),
-            //     FILE_CMAKE_BUILD => Some(ProjectType::CMake),
-            //     FILE_COMPOSER_JSON => Some(ProjectType::Composer),
-            //     _ => None,
-            // };
-            if let Some(project_type) = get_project_type(&file_name) {
-                return Some(Project {
-                    project_type,
-                    path: project_path.into(),
-                });
+impl Error for ScanJsonError {}
+
+impl From<io::Error> for ScanJsonError {
+    fn from(e: io::Error) -> Self {
+        ScanJsonError::IOError(e)
+    }
+}
+
+/// One artifact dir removed by `Project::clean_with_log`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CleanLogEntry {
+    pub timestamp: time::SystemTime,
+    pub project_path: path::PathBuf,
+    pub artifact_dir: path::PathBuf,
+    pub bytes_freed: u64,
+}
+
+impl CleanLogEntry {
+    /// The timestamp is written as whole seconds since the Unix epoch
+    fn to_json(&self) -> String {
+        let timestamp = self
+            .timestamp
+            .duration_since(time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        format!(
+            "{{\"timestamp\":{},\"project_path\":{},\"artifact_dir\":{},\"bytes_freed\":{}}}",
+            timestamp,
+            json_string(&self.project_path.to_string_lossy()),
+            json_string(&self.artifact_dir.to_string_lossy()),
+            self.bytes_freed
+        )
+    }
+
+    fn from_json(line: &str) -> Option<CleanLogEntry> {
+        let mut timestamp = None;
+        let mut project_path = None;
+        let mut artifact_dir = None;
+        let mut bytes_freed = None;
+
+        let mut rest = line.trim().strip_prefix('{')?.trim_start();
+        while !rest.starts_with('}') {
+            let (key, after_key) = parse_json_string(rest)?;
+            rest = after_key.trim_start().strip_prefix(':')?.trim_start();
+            if rest.starts_with('"') {
+                let (value, after_value) = parse_json_string(rest)?;
+                match key.as_str() {
+                    "project_path" => project_path = Some(path::PathBuf::from(value)),
+                    "artifact_dir" => artifact_dir = Some(path::PathBuf::from(value)),
+                    _ => {}
+                }
+                rest = after_value;
+            } else {
+                let end = rest
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(rest.len());
+                let value: u64 = rest[..end].parse().ok()?;
+                match key.as_str() {
+                    "timestamp" => {
+                        timestamp = Some(time::UNIX_EPOCH + time::Duration::from_secs(value))
+                    }
+                    "bytes_freed" => bytes_freed = Some(value),
+                    _ => {}
+                }
+                rest = &rest[end..];
             }
-            None
+            rest = rest.trim_start();
+            if let Some(after_comma) = rest.strip_prefix(',') {
+                rest = after_comma.trim_start();
+            } else if !rest.starts_with('}') {
+                return None;
+            }
+        }
+
+        Some(CleanLogEntry {
+            timestamp: timestamp?,
+            project_path: project_path?,
+            artifact_dir: artifact_dir?,
+            bytes_freed: bytes_freed?,
         })
-        .map(|x| {
-            x.artifact_dirs()
-                .into_par_iter()
-                .copied()
-                .map(|ad| path::PathBuf::from(project_path).join(ad))
-                .filter(|ad| ad.exists())
-                .try_for_each(|x| {
-                    if let Err(e) = fs::remove_dir_all(x) {
-                        eprintln!("error removing directory {:?}: {:?}", x, e);
-                        return Err(e);
-                    }
-                    Ok(())
-                })
-        });
+    }
+}
 
-    if let Some(project) = project {
-        for artifact_dir in project
-            .artifact_dirs()
-            .iter()
-            .copied()
-            .map(|ad| path::PathBuf::from(project_path).join(ad))
-            .filter(|ad| ad.exists())
-        {
-            if let Err(e) = fs::remove_dir_all(&artifact_dir) {
-                eprintln!("error removing directory {:?}: {:?}", artifact_dir, e);
+/// Reads back a log written by `Project::clean_with_log`, oldest entry first
+pub fn read_clean_log(log_path: &path::Path) -> Result<Vec<CleanLogEntry>, io::Error> {
+    fs::read_to_string(log_path)?
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            CleanLogEntry::from_json(line).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid clean log entry on line {}", i + 1),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Parses the JSON string at the start of `s`, returning it and the text after it
+fn parse_json_string(s: &str) -> Option<(String, &str)> {
+    let mut chars = s.strip_prefix('"')?.char_indices();
+    let mut out = String::new();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((out, &s[i + 2..])),
+            '\\' => match chars.next()?.1 {
+                'n' => out.push('\n'),
+                'r' => out.push('\r'),
+                't' => out.push('\t'),
+                'u' => {
+                    let hex: String = (0..4)
+                        .filter_map(|_| chars.next())
+                        .map(|(_, c)| c)
+                        .collect();
+                    out.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                }
+                c => out.push(c),
+            },
+            c => out.push(c),
+        }
+    }
+    None
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Scans `p` and writes one JSON object per line to `writer`, returning the number of projects written
+///
+/// Projects are written as `{"path":...,"type":...,"artifact_size":...}` and scan errors as
+/// `{"error":...,"path":...}`, where the error path is `null` if it isn't known.
+pub fn scan_ndjson<P, W>(p: &P, mut writer: W) -> Result<u64, ScanJsonError>
+where
+    P: AsRef<path::Path>,
+    W: io::Write + Send,
+{
+    let mut count = 0;
+    for result in scan(p) {
+        match result {
+            Ok(project) => {
+                writeln!(
+                    writer,
+                    "{{\"path\":{},\"type\":{},\"artifact_size\":{}}}",
+                    json_string(&project.path.to_string_lossy()),
+                    json_string(project.type_name()),
+                    project.size()
+                )?;
+                count += 1;
+            }
+            Err(e) => {
+                let path = match &e {
+                    Red::WalkdirError(we) => we
+                        .path()
+                        .map(|p| json_string(&p.to_string_lossy()))
+                        .unwrap_or_else(|| "null".to_string()),
+                    Red::NetworkFilesystem { path, .. } => json_string(&path.to_string_lossy()),
+                    Red::IOError(_) => "null".to_string(),
+                };
+                writeln!(
+                    writer,
+                    "{{\"error\":{},\"path\":{}}}",
+                    json_string(&e.to_string()),
+                    path
+                )?;
             }
         }
     }
+    writer.flush()?;
+    Ok(count)
+}
 
-    Ok(())
+/// What `scan_to_dot_with_options` puts in the graph
+#[derive(Debug, Clone)]
+pub struct DotOptions {
+    /// Add each project's artifact size to its label, this measures every project
+    pub show_sizes: bool,
+    pub show_types: bool,
+    /// Graphviz `rankdir`, e.g. `TB` or `LR`
+    pub rankdir: String,
 }
-pub fn path_canonicalise(
-    base: &path::Path,
-    tail: path::PathBuf,
-) -> Result<path::PathBuf, Box<dyn Error>> {
-    if tail.is_absolute() {
-        Ok(tail)
-    } else {
-        Ok(base.join(tail).canonicalize()?)
+
+impl Default for DotOptions {
+    fn default() -> Self {
+        DotOptions {
+            show_sizes: true,
+            show_types: true,
+            rankdir: "TB".to_string(),
+        }
+    }
+}
+
+/// Quotes `s` as a DOT string, newlines become DOT's `\n` line breaks
+fn dot_string(s: &str) -> String {
+    format!(
+        "\"{}\"",
+        s.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+/// Scans `p` and writes a Graphviz DOT graph of the projects found, with an edge from each
+/// project to those nested directly inside it. Scan errors are left out.
+pub fn scan_to_dot<P: AsRef<path::Path>>(p: &P, writer: &mut impl io::Write) -> io::Result<()> {
+    scan_to_dot_with_options(p, writer, &DotOptions::default())
+}
+
+pub fn scan_to_dot_with_options<P: AsRef<path::Path>>(
+    p: &P,
+    writer: &mut impl io::Write,
+    options: &DotOptions,
+) -> io::Result<()> {
+    let projects: Vec<Project> = scan(p).filter_map(|r| r.ok()).collect();
+
+    writeln!(writer, "digraph {{")?;
+    writeln!(writer, "    rankdir={};", dot_string(&options.rankdir))?;
+    for (i, project) in projects.iter().enumerate() {
+        let mut label = project.path.to_string_lossy().into_owned();
+        if options.show_types {
+            label.push_str(&format!("\n{}", project.type_name()));
+        }
+        if options.show_sizes {
+            let size = project.pre_computed_size.unwrap_or_else(|| project.size());
+            label.push_str(&format!("\n{}", pretty_size(size)));
+        }
+        writeln!(writer, "    n{} [label={}];", i, dot_string(&label))?;
+    }
+    for (i, project) in projects.iter().enumerate() {
+        let parent = projects
+            .iter()
+            .enumerate()
+            .filter(|(_, other)| project.is_nested_inside(other))
+            .max_by_key(|(_, other)| other.path.components().count());
+        if let Some((parent, _)) = parent {
+            writeln!(writer, "    n{} -> n{};", parent, i)?;
+        }
+    }
+    writeln!(writer, "}}")?;
+    writer.flush()
+}
+
+pub fn dir_size(path: &path::Path) -> u64 {
+    dir_size_with(path, Parallelism::RayonDefaultPool)
+}
+
+/// Files counted by `dir_size_streaming` between updates
+const STREAMING_BATCH_SIZE: u64 = 1000;
+
+/// Like `dir_size`, sending the running total over `sender` every few files and once more at
+/// the end, for showing progress on huge directories. Updates stop if the receiver hangs up,
+/// the walk carries on.
+pub fn dir_size_streaming(path: &path::Path, sender: std::sync::mpsc::Sender<u64>) -> u64 {
+    let mut total = 0;
+    let mut files = 0;
+    for len in jwalk::WalkDir::new(path)
+        .follow_links(SYMLINK_FOLLOW)
+        .parallelism(Parallelism::RayonDefaultPool)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.metadata().ok())
+        .map(|m| m.len())
+    {
+        total += len;
+        files += 1;
+        if files % STREAMING_BATCH_SIZE == 0 {
+            let _ = sender.send(total);
+        }
+    }
+    let _ = sender.send(total);
+    total
+}
+
+/// Like `dir_size`, but files hard linked more than once within `path` are only counted once,
+/// which is closer to the disk space actually freed for e.g. Nix or Bazel outputs
+#[cfg(unix)]
+pub fn dir_size_dedup(path: &path::Path) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+
+    let mut seen = std::collections::HashSet::new();
+    jwalk::WalkDir::new(path)
+        .follow_links(SYMLINK_FOLLOW)
+        .parallelism(Parallelism::RayonDefaultPool)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.nlink() <= 1 || seen.insert((m.dev(), m.ino())))
+        .map(|m| m.len())
+        .sum()
+}
+
+/// Like `dir_size`, but meant to count hard linked files only once. Windows only exposes
+/// file ids through unstable std APIs, so this is plain `dir_size` there for now.
+#[cfg(not(unix))]
+pub fn dir_size_dedup(path: &path::Path) -> u64 {
+    dir_size(path)
+}
+
+/// Disk space taken by the files within `path`, as opposed to `dir_size` which sums their
+/// lengths. Small files round up to whole blocks, so this is usually larger, while sparse or
+/// compressed files can make it smaller. Hard links are counted each time, as in `dir_size`.
+pub fn dir_disk_usage(path: &path::Path) -> u64 {
+    jwalk::WalkDir::new(path)
+        .follow_links(SYMLINK_FOLLOW)
+        .parallelism(Parallelism::RayonDefaultPool)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| Some(allocated_size(&e.path(), &e.metadata().ok()?)))
+        .sum()
+}
+
+#[cfg(unix)]
+fn allocated_size(_path: &path::Path, metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+
+    // st_blocks is always in 512 byte units, whatever the filesystem's block size
+    metadata.blocks() * 512
+}
+
+#[cfg(windows)]
+fn allocated_size(path: &path::Path, metadata: &fs::Metadata) -> u64 {
+    use std::os::windows::ffi::OsStrExt;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetCompressedFileSizeW(file_name: *const u16, file_size_high: *mut u32) -> u32;
+        fn SetLastError(error: u32);
+    }
+    const INVALID_FILE_SIZE: u32 = u32::MAX;
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut high = 0;
+    // a low word of INVALID_FILE_SIZE is only an error if the last error was set
+    let low = unsafe {
+        SetLastError(0);
+        GetCompressedFileSizeW(wide.as_ptr(), &mut high)
+    };
+    if low == INVALID_FILE_SIZE && io::Error::last_os_error().raw_os_error() != Some(0) {
+        return metadata.len();
+    }
+    (u64::from(high) << 32) | u64::from(low)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn allocated_size(_path: &path::Path, metadata: &fs::Metadata) -> u64 {
+    metadata.len()
+}
+
+fn dir_size_with(path: &path::Path, parallelism: Parallelism) -> u64 {
+    jwalk::WalkDir::new(path)
+        .follow_links(SYMLINK_FOLLOW)
+        .parallelism(parallelism)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.metadata().ok())
+        .map(|e| e.len())
+        .sum()
+}
+
+/// Returns the most recent modification time of any file within `path`
+pub fn dir_age(path: &path::Path) -> Option<time::SystemTime> {
+    jwalk::WalkDir::new(path)
+        .follow_links(SYMLINK_FOLLOW)
+        .parallelism(Parallelism::RayonDefaultPool)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.metadata().ok())
+        .filter_map(|m| m.modified().ok())
+        .max()
+}
+
+fn dir_size_and_count(path: &path::Path) -> (u64, u64) {
+    jwalk::WalkDir::new(path)
+        .follow_links(SYMLINK_FOLLOW)
+        .parallelism(Parallelism::RayonDefaultPool)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.metadata().ok())
+        .fold((0, 0), |(size, count), m| (size + m.len(), count + 1))
+}
+
+pub fn pretty_size(size: u64) -> String {
+    const KIBIBYTE: u64 = 1024;
+    const MEBIBYTE: u64 = 1_048_576;
+    const GIBIBYTE: u64 = 1_073_741_824;
+    const TEBIBYTE: u64 = 1_099_511_627_776;
+    const PEBIBYTE: u64 = 1_125_899_906_842_624;
+    const EXBIBYTE: u64 = 1_152_921_504_606_846_976;
+
+    let (unit, symbol) = match size {
+        size if size < KIBIBYTE => (1, "B"),
+        size if size < MEBIBYTE => (KIBIBYTE, "KiB"),
+        size if size < GIBIBYTE => (MEBIBYTE, "MiB"),
+        size if size < TEBIBYTE => (GIBIBYTE, "GiB"),
+        size if size < PEBIBYTE => (TEBIBYTE, "TiB"),
+        size if size < EXBIBYTE => (PEBIBYTE, "PiB"),
+        _ => (EXBIBYTE, "EiB"),
+    };
+
+    // Integer equivalent of `format!("{:.1}", size as f64 / unit as f64)`, which spent most
+    // of its time in float formatting. Sizes go through f64 first to round huge values the
+    // same way, and ties round to even like float formatting does.
+    let unit = u128::from(unit);
+    let scaled = (size as f64) as u128 * 10;
+    let mut tenths = scaled / unit;
+    let remainder = (scaled % unit) * 2;
+    if remainder > unit || (remainder == unit && tenths % 2 == 1) {
+        tenths += 1;
+    }
+
+    format!("{}.{}{}", tenths / 10, tenths % 10, symbol)
+}
+#[derive(Debug, Clone)]
+pub struct MultiError<E: Error> {
+    errs: Vec<E>,
+    success: Vec<Project>,
+}
+
+impl<E: Error> fmt::Display for MultiError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} projects found, {} errors",
+            self.success.len(),
+            self.errs.len()
+        )
+    }
+}
+
+impl<E: Error> Error for MultiError<E> {}
+
+impl<E: Error> MultiError<E> {
+    pub fn errs(&self) -> &Vec<E> {
+        &self.errs
+    }
+    pub fn success(&self) -> &Vec<Project> {
+        &self.success
+    }
+    pub fn has_errors(&self) -> bool {
+        !self.errs.is_empty()
+    }
+    pub fn error_count(&self) -> usize {
+        self.errs.len()
+    }
+    /// Fails with the first collected error, or returns every project if there were none
+    pub fn into_result(self) -> Result<Vec<Project>, E> {
+        match self.errs.into_iter().next() {
+            None => Ok(self.success),
+            Some(e) => Err(e),
+        }
+    }
+    /// Like `into_result`, but keeps everything collected when there were errors
+    pub fn into_projects(self) -> Result<Vec<Project>, Self> {
+        if self.has_errors() {
+            Err(self)
+        } else {
+            Ok(self.success)
+        }
+    }
+    /// Splits into the found projects and the errors
+    pub fn partition(self) -> (Vec<Project>, Vec<E>) {
+        (self.success, self.errs)
+    }
+}
+
+impl<E: Error> FromIterator<Result<Project, E>> for MultiError<E> {
+    fn from_iter<T: IntoIterator<Item = Result<Project, E>>>(iter: T) -> Self {
+        let mut res = MultiError {
+            errs: Vec::new(),
+            success: Vec::new(),
+        };
+        iter.into_iter().for_each(|x: Result<Project, E>| match x {
+            Ok(p) => res.success.push(p),
+            Err(e) => res.errs.push(e),
+        });
+        res
+    }
+}
+
+pub fn clean(project_path: &str) -> Result<(), Box<dyn error::Error>> {
+    let project = detect_project_type_in(fs::read_dir(project_path)?, CASE_INSENSITIVE_FS)
+        .map(|project_type| Project::new(project_type, project_path.into()));
+
+    if let Some(project) = project {
+        for artifact_dir in project
+            .artifact_dirs()
+            .iter()
+            .copied()
+            .map(|ad| path::PathBuf::from(project_path).join(ad))
+            .filter(|ad| is_existing_artifact(ad))
+        {
+            if let Err(e) = remove_artifact_dir(&artifact_dir) {
+                eprintln!("error removing directory {:?}: {:?}", artifact_dir, e);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Cleans every project in parallel, summarising the results
+pub fn clean_all_with_stats<'a>(
+    projects: impl IntoIterator<Item = &'a Project>,
+) -> BatchCleanReport {
+    let start = time::Instant::now();
+    let projects: Vec<&Project> = projects.into_iter().collect();
+    let clean = |p: &&Project| {
+        p.clean_with_options(&CleanOptions {
+            measure_sizes: true,
+            ..CleanOptions::default()
+        })
+    };
+    // cleaning measures sizes with jwalk on the global pool, so clean on a separate pool
+    // rather than tying up the threads those walks need
+    let results: Vec<_> = match rayon::ThreadPoolBuilder::new().build() {
+        Ok(pool) => pool.install(|| projects.par_iter().map(clean).collect()),
+        Err(_) => projects.iter().map(clean).collect(),
+    };
+
+    let mut report = BatchCleanReport {
+        total_bytes_freed: 0,
+        projects_cleaned: 0,
+        projects_failed: 0,
+        errors: Vec::new(),
+        duration: time::Duration::default(),
+    };
+    for result in results {
+        match result {
+            Ok(result) => {
+                report.total_bytes_freed += result.bytes_freed;
+                report.projects_cleaned += 1;
+            }
+            Err(errors) => {
+                report.projects_failed += 1;
+                report.errors.extend(errors);
+            }
+        }
+    }
+    report.duration = start.elapsed();
+    report
+}
+
+/// Cache directories under the user's home that tools of `project_type` share between all
+/// their projects, whether or not they exist. Empty for tools without one.
+pub fn global_cache_dirs(project_type: &ProjectType) -> Vec<path::PathBuf> {
+    let names: &[&str] = match project_type {
+        ProjectType::Nx => &GLOBAL_CACHE_NX_DIRS,
+        ProjectType::Turborepo => &GLOBAL_CACHE_TURBOREPO_DIRS,
+        _ => &[],
+    };
+    match home_dir() {
+        Some(home) => names.iter().map(|name| home.join(name)).collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Removes the existing `global_cache_dirs` of `project_type`. This affects every project of
+/// that type, not any one in particular.
+pub fn clean_global_cache(project_type: &ProjectType) -> Result<CleanResult, Vec<CleanError>> {
+    let mut result = CleanResult::default();
+    let mut errors = Vec::new();
+    for dir in global_cache_dirs(project_type) {
+        if dir.symlink_metadata().is_err() {
+            continue;
+        }
+        let size = dir_size(&dir);
+        match remove_artifact_dir(&dir) {
+            Ok(()) => {
+                result.bytes_freed += size;
+                result.removed.push(dir);
+            }
+            Err(error) => errors.push(CleanError::IOError { path: dir, error }),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(result)
+    } else {
+        Err(errors)
+    }
+}
+
+fn home_dir() -> Option<path::PathBuf> {
+    let var = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+    std::env::var_os(var)
+        .filter(|home| !home.is_empty())
+        .map(path::PathBuf::from)
+}
+
+pub fn path_canonicalise(base: &path::Path, tail: path::PathBuf) -> io::Result<path::PathBuf> {
+    if tail.is_absolute() {
+        Ok(tail)
+    } else {
+        Ok(base.join(tail).canonicalize()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A fresh directory under the system temp dir, removed again on drop
+    struct TempDir(path::PathBuf);
+
+    impl TempDir {
+        fn new() -> TempDir {
+            static COUNT: AtomicUsize = AtomicUsize::new(0);
+            let path = std::env::temp_dir().join(format!(
+                "kondo-test-{}-{}",
+                std::process::id(),
+                COUNT.fetch_add(1, Ordering::SeqCst)
+            ));
+            fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+
+        fn path(&self) -> &path::Path {
+            &self.0
+        }
+
+        /// Writes `contents` to `rel`, creating any missing parent directories
+        fn file(&self, rel: &str, contents: &str) -> path::PathBuf {
+            let path = self.0.join(rel);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, contents).unwrap();
+            path
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn clean_preview_lists_every_artifact_dir() {
+        let dir = TempDir::new();
+        dir.file("build.sbt", "");
+        dir.file("target/a", "12345");
+        dir.file("target/nested/b", "123");
+        let project = Project::detect_in(dir.path()).unwrap();
+
+        let preview = project.clean_preview();
+        assert_eq!(preview.dirs.len(), 2);
+        let target = &preview.dirs[0];
+        assert_eq!(target.path, dir.path().join("target"));
+        assert!(target.exists);
+        assert_eq!((target.size, target.file_count), (8, 2));
+        let project_target = &preview.dirs[1];
+        assert_eq!(project_target.path, dir.path().join("project/target"));
+        assert!(!project_target.exists);
+        assert_eq!((project_target.size, project_target.file_count), (0, 0));
+        assert_eq!(preview.total_size(), 8);
+
+        let text = preview.to_string();
+        assert!(text.contains("2 files"), "{}", text);
+        assert!(text.contains("(missing)"), "{}", text);
+        assert!(text.ends_with("total: 8.0B"), "{}", text);
+        assert!(dir.path().join("target/a").exists());
+    }
+
+    fn multi_error(results: Vec<Result<Project, io::Error>>) -> MultiError<io::Error> {
+        results.into_iter().collect()
+    }
+
+    fn project_at(path: &str) -> Project {
+        Project::new(ProjectType::Cargo, path.into())
+    }
+
+    #[test]
+    fn multi_error_into_result_all_success() {
+        let errors = multi_error(vec![Ok(project_at("a")), Ok(project_at("b"))]);
+        assert!(!errors.has_errors());
+        assert_eq!(errors.error_count(), 0);
+        let projects = errors.into_result().unwrap();
+        let paths: Vec<_> = projects.iter().map(|p| p.path.clone()).collect();
+        assert_eq!(paths, [path::PathBuf::from("a"), path::PathBuf::from("b")]);
+    }
+
+    #[test]
+    fn multi_error_into_result_all_error() {
+        let errors = multi_error(vec![
+            Err(io::Error::new(io::ErrorKind::NotFound, "first")),
+            Err(io::Error::new(io::ErrorKind::PermissionDenied, "second")),
+        ]);
+        assert!(errors.has_errors());
+        assert_eq!(errors.error_count(), 2);
+        let error = errors.into_result().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn multi_error_into_result_mixed() {
+        let errors = multi_error(vec![
+            Ok(project_at("a")),
+            Err(io::Error::new(io::ErrorKind::NotFound, "first")),
+            Ok(project_at("b")),
+        ]);
+        assert!(errors.has_errors());
+        assert_eq!(errors.error_count(), 1);
+        assert_eq!(errors.success().len(), 2);
+        assert_eq!(errors.to_string(), "2 projects found, 1 errors");
+        assert_eq!(errors.into_result().unwrap_err().to_string(), "first");
+    }
+
+    fn walk_error(dir: &TempDir) -> jwalk::Error {
+        jwalk::WalkDir::new(dir.path().join("missing"))
+            .into_iter()
+            .find_map(|e| e.err())
+            .unwrap()
+    }
+
+    #[test]
+    fn red_from_and_into_io_error() {
+        let red = Red::from(io::Error::new(io::ErrorKind::PermissionDenied, "denied"));
+        assert!(matches!(red, Red::IOError(_)));
+        assert_eq!(red.to_string(), "io error: denied");
+        assert!(red.source().is_some());
+
+        let error = io::Error::from(red);
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(error.to_string(), "denied");
+    }
+
+    #[test]
+    fn red_from_and_into_walk_error() {
+        let dir = TempDir::new();
+        let red = Red::from(walk_error(&dir));
+        assert!(matches!(red, Red::WalkdirError(_)));
+        assert!(red.to_string().starts_with("walk error: "));
+        assert!(red.source().is_some());
+
+        let error = io::Error::from(red);
+        assert_eq!(error.kind(), io::ErrorKind::Other);
+    }
+
+    fn set_modified(path: &path::Path, modified: time::SystemTime) {
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    #[test]
+    fn audit_finds_stale_artifacts_and_missing_lockfile() {
+        let dir = TempDir::new();
+        dir.file("Cargo.toml", "[package]");
+        let old = dir.file("target/debug/old", "");
+        set_modified(
+            &old,
+            time::SystemTime::now() - time::Duration::from_secs(40 * 86_400),
+        );
+
+        let findings = Project::detect_in(dir.path()).unwrap().audit();
+        assert_eq!(findings.len(), 2, "{:?}", findings);
+        assert!(findings
+            .iter()
+            .any(|f| matches!(f, AuditFinding::StaleArtifacts { days } if *days >= 40)));
+        assert!(findings.contains(&AuditFinding::MissingLockfile));
+        assert_eq!(AuditFinding::MissingLockfile.severity(), Severity::Low);
+    }
+
+    #[test]
+    fn audit_of_a_clean_project_finds_nothing() {
+        let dir = TempDir::new();
+        dir.file("package.json", "{}");
+        dir.file("package-lock.json", "{}");
+        dir.file("node_modules/left-pad/index.js", "");
+        assert!(Project::detect_in(dir.path()).unwrap().audit().is_empty());
+    }
+
+    #[test]
+    fn audit_counts_dependencies() {
+        let dir = TempDir::new();
+        dir.file("package.json", "{}");
+        dir.file("package-lock.json", "{}");
+        for i in 0..=AUDIT_DEPENDENCY_COUNT {
+            dir.file(&format!("node_modules/dep-{}/index.js", i), "");
+        }
+        let findings = Project::detect_in(dir.path()).unwrap().audit();
+        assert_eq!(
+            findings,
+            [AuditFinding::OversizedDependencies {
+                count: AUDIT_DEPENDENCY_COUNT + 1
+            }]
+        );
+    }
+
+    #[test]
+    fn audit_skips_lockfile_check_for_workspace_members() {
+        let dir = TempDir::new();
+        dir.file("Cargo.toml", "[workspace]\nmembers = [\"a\"]\n");
+        dir.file("Cargo.lock", "");
+        dir.file("a/Cargo.toml", "[package]");
+        let member = Project::detect_in(&dir.path().join("a")).unwrap();
+        assert!(member.audit().is_empty());
+    }
+
+    /// Parses one flat NDJSON object of strings, numbers and nulls, as written by `scan_ndjson`
+    fn parse_json_object(line: &str) -> std::collections::BTreeMap<String, String> {
+        fn string(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> String {
+            assert_eq!(chars.next(), Some('"'));
+            let mut out = String::new();
+            loop {
+                match chars.next().unwrap() {
+                    '"' => return out,
+                    '\\' => match chars.next().unwrap() {
+                        'n' => out.push('\n'),
+                        'r' => out.push('\r'),
+                        't' => out.push('\t'),
+                        'u' => {
+                            let hex: String = chars.by_ref().take(4).collect();
+                            out.push(
+                                char::from_u32(u32::from_str_radix(&hex, 16).unwrap()).unwrap(),
+                            );
+                        }
+                        c => out.push(c),
+                    },
+                    c => out.push(c),
+                }
+            }
+        }
+
+        let mut object = std::collections::BTreeMap::new();
+        let mut chars = line.chars().peekable();
+        assert_eq!(chars.next(), Some('{'));
+        loop {
+            let key = string(&mut chars);
+            assert_eq!(chars.next(), Some(':'));
+            let value = if chars.peek() == Some(&'"') {
+                string(&mut chars)
+            } else {
+                let mut raw = String::new();
+                while let Some(c) = chars.next_if(|c| *c != ',' && *c != '}') {
+                    raw.push(c);
+                }
+                raw
+            };
+            object.insert(key, value);
+            match chars.next() {
+                Some(',') => continue,
+                Some('}') => break,
+                c => panic!("unexpected {:?} in {}", c, line),
+            }
+        }
+        assert_eq!(chars.next(), None);
+        object
+    }
+
+    #[test]
+    fn scan_ndjson_writes_one_object_per_project() {
+        let dir = TempDir::new();
+        dir.file("app/package.json", "{}");
+        dir.file("app/node_modules/a", "12345");
+        dir.file("quoted \"dir\"\n/Cargo.toml", "[package]");
+
+        let mut out = Vec::new();
+        let count = scan_ndjson(&dir.path(), &mut out).unwrap();
+        assert_eq!(count, 2);
+
+        let mut objects: Vec<_> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(parse_json_object)
+            .collect();
+        objects.sort_by(|a, b| a["path"].cmp(&b["path"]));
+        assert_eq!(objects.len(), 2);
+        assert_eq!(objects[0]["path"], dir.path().join("app").to_string_lossy());
+        assert_eq!(objects[0]["type"], "Node");
+        assert_eq!(objects[0]["artifact_size"], "5");
+        assert_eq!(
+            objects[1]["path"],
+            dir.path().join("quoted \"dir\"\n").to_string_lossy()
+        );
+        assert_eq!(objects[1]["type"], "Cargo");
+        assert_eq!(objects[1]["artifact_size"], "0");
+    }
+
+    #[test]
+    fn scan_ndjson_writes_errors() {
+        let dir = TempDir::new();
+        let mut out = Vec::new();
+        let count = scan_ndjson(&dir.path().join("missing"), &mut out).unwrap();
+        assert_eq!(count, 0);
+
+        let out = String::from_utf8(out).unwrap();
+        let objects: Vec<_> = out.lines().map(parse_json_object).collect();
+        assert_eq!(objects.len(), 1);
+        assert!(objects[0].contains_key("error"));
+        assert!(objects[0].contains_key("path"));
+    }
+
+    #[test]
+    fn buck_packages_inside_a_cell_are_not_projects() {
+        let dir = TempDir::new();
+        dir.file(".buckconfig", "");
+        dir.file("a/BUCK", "");
+        dir.file("b/c/BUCK", "");
+        dir.file("standalone/BUCK", "");
+
+        let projects: Vec<_> = scan(&dir.path()).filter_map(|p| p.ok()).collect();
+        assert_eq!(projects.len(), 1);
+        assert!(matches!(projects[0].project_type, ProjectType::Buck));
+        assert_eq!(projects[0].path, dir.path());
+    }
+
+    #[test]
+    fn buck_file_without_buckconfig_is_a_project() {
+        let dir = TempDir::new();
+        dir.file("BUCK", "");
+        dir.file("buck-out/v2/gen/a", "a");
+
+        let project = Project::detect_in(dir.path()).unwrap();
+        assert!(matches!(project.project_type, ProjectType::Buck));
+        project
+            .clean_with_options(&CleanOptions::default())
+            .unwrap();
+        assert!(!dir.path().join("buck-out").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_buck_out_only_removes_the_link() {
+        let dir = TempDir::new();
+        dir.file("cache/v2/gen/a", "a");
+        dir.file("repo/.buckconfig", "");
+        std::os::unix::fs::symlink(dir.path().join("cache"), dir.path().join("repo/buck-out"))
+            .unwrap();
+
+        let project = Project::detect_in(&dir.path().join("repo")).unwrap();
+        assert!(matches!(project.project_type, ProjectType::Buck));
+        project
+            .clean_with_options(&CleanOptions::default())
+            .unwrap();
+        assert!(fs::symlink_metadata(dir.path().join("repo/buck-out")).is_err());
+        assert!(dir.path().join("cache/v2/gen/a").exists());
+    }
+
+    #[test]
+    fn pants_project_detected_and_cleaned() {
+        let dir = TempDir::new();
+        dir.file("pants.toml", "[GLOBAL]\n");
+        dir.file(".pants.d/run/a", "a");
+        dir.file("dist/app.pex", "pex");
+        dir.file("src/main.py", "");
+
+        let project = Project::detect_in(dir.path()).unwrap();
+        assert!(matches!(project.project_type, ProjectType::Pants));
+        assert_eq!(project.type_name(), "Pants");
+        project
+            .clean_with_options(&CleanOptions::default())
+            .unwrap();
+        assert!(!dir.path().join(".pants.d").exists());
+        assert!(!dir.path().join("dist").exists());
+        assert!(dir.path().join("src/main.py").exists());
+    }
+
+    #[test]
+    fn clean_uses_detection_priority() {
+        let dir = TempDir::new();
+        dir.file("nx/nx.json", "{}");
+        dir.file("nx/package.json", "{}");
+        dir.file("nx/dist/main.js", "");
+        dir.file("poetry/pyproject.toml", "[tool.poetry]\nname = \"a\"\n");
+        dir.file("poetry/main.py", "");
+        dir.file("poetry/dist/a.whl", "");
+
+        for name in ["nx", "poetry"] {
+            clean(dir.path().join(name).to_str().unwrap()).unwrap();
+            assert!(!dir.path().join(name).join("dist").exists(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn pyproject_with_poetry_section_is_poetry() {
+        let dir = TempDir::new();
+        dir.file(
+            "pyproject.toml",
+            "[tool.poetry]\nname = \"app\"\n\n[build-system]\nrequires = [\"poetry-core\"]\n",
+        );
+        dir.file(".venv/lib/a", "");
+        dir.file("dist/app.whl", "");
+
+        let project = Project::detect_in(dir.path()).unwrap();
+        assert!(matches!(project.project_type, ProjectType::Poetry));
+        project
+            .clean_with_options(&CleanOptions::default())
+            .unwrap();
+        assert!(!dir.path().join(".venv").exists());
+        assert!(!dir.path().join("dist").exists());
+        assert!(dir.path().join("pyproject.toml").exists());
+    }
+
+    #[test]
+    fn pyproject_without_poetry_section_is_not_poetry() {
+        let dir = TempDir::new();
+        dir.file("plain/pyproject.toml", "[project]\nname = \"app\"\n");
+        assert!(Project::detect_in(&dir.path().join("plain")).is_none());
+
+        dir.file("python/pyproject.toml", "[project]\nname = \"app\"\n");
+        dir.file("python/main.py", "");
+        let project = Project::detect_in(&dir.path().join("python")).unwrap();
+        assert!(matches!(project.project_type, ProjectType::Python));
+    }
+
+    #[test]
+    fn pdm_detected_by_lockfile_or_pyproject_section() {
+        let dir = TempDir::new();
+        dir.file("lock/pdm.lock", "");
+        dir.file("lock/.pdm-python", "/usr/bin/python3");
+        dir.file("lock/__pycache__/a.pyc", "");
+        dir.file(
+            "section/pyproject.toml",
+            "[tool.pdm]\ndistribution = true\n",
+        );
+        dir.file("suffix/my-pdm.lock", "");
+
+        let lock = Project::detect_in(&dir.path().join("lock")).unwrap();
+        assert!(matches!(lock.project_type, ProjectType::PDM));
+        let section = Project::detect_in(&dir.path().join("section")).unwrap();
+        assert!(matches!(section.project_type, ProjectType::PDM));
+        assert!(Project::detect_in(&dir.path().join("suffix")).is_none());
+
+        lock.clean_with_options(&CleanOptions::default()).unwrap();
+        assert!(!dir.path().join("lock/.pdm-python").exists());
+        assert!(!dir.path().join("lock/__pycache__").exists());
+        assert!(dir.path().join("lock/pdm.lock").exists());
+    }
+
+    #[test]
+    fn hatch_toml_wins_over_pyproject() {
+        let dir = TempDir::new();
+        dir.file("hatch.toml", "[envs.default]\n");
+        dir.file("pyproject.toml", "[tool.poetry]\nname = \"app\"\n");
+        dir.file(".hatch/env/a", "");
+        dir.file("dist/app.whl", "");
+        dir.file(".venv/lib/a", "");
+
+        let project = Project::detect_in(dir.path()).unwrap();
+        assert!(matches!(project.project_type, ProjectType::Hatch));
+        project
+            .clean_with_options(&CleanOptions::default())
+            .unwrap();
+        assert!(!dir.path().join(".hatch").exists());
+        assert!(!dir.path().join("dist").exists());
+        assert!(dir.path().join(".venv/lib/a").exists());
+    }
+
+    #[test]
+    fn regular_file_named_like_an_artifact_dir_survives_clean() {
+        let dir = TempDir::new();
+        for (name, manifest, contents) in [
+            ("poetry", "pyproject.toml", "[tool.poetry]\n"),
+            ("hatch", "hatch.toml", "[envs.default]\n"),
+        ] {
+            dir.file(&format!("{}/{}", name, manifest), contents);
+            dir.file(&format!("{}/dist", name), "#!/bin/sh\n");
+            dir.file(&format!("{}/__pycache__/a.pyc", name), "");
+
+            let project = Project::detect_in(&dir.path().join(name)).unwrap();
+            let result = project
+                .clean_with_options(&CleanOptions::default())
+                .unwrap();
+            assert_eq!(result.removed, [dir.path().join(name).join("__pycache__")]);
+            assert!(dir.path().join(name).join("dist").is_file(), "{}", name);
+            project.clean();
+            clean(dir.path().join(name).to_str().unwrap()).unwrap();
+            assert!(dir.path().join(name).join("dist").is_file(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn related_projects_are_the_sibling_projects() {
+        let dir = TempDir::new();
+        dir.file("mono/web/package.json", "{}");
+        dir.file("mono/api/Cargo.toml", "[package]");
+        dir.file("mono/cli/Cargo.toml", "[package]");
+        dir.file("mono/docs/README.md", "");
+        dir.file("mono/notes.txt", "");
+
+        let project = Project::detect_in(&dir.path().join("mono/cli")).unwrap();
+        let mut related: Vec<_> = project
+            .related_projects()
+            .into_iter()
+            .map(|p| p.path)
+            .collect();
+        related.sort();
+        assert_eq!(
+            related,
+            [dir.path().join("mono/api"), dir.path().join("mono/web")]
+        );
+        assert_eq!(project.related_project_count(), 2);
+    }
+
+    #[cfg(target_os = "linux")]
+    const MOUNTS_FIXTURE: &str = "\
+sysfs /sys sysfs rw,nosuid,nodev,noexec,relatime 0 0
+proc /proc proc rw,nosuid,nodev,noexec,relatime 0 0
+/dev/nvme0n1p2 / ext4 rw,relatime 0 0
+tmpfs /tmp tmpfs rw,nosuid,nodev 0 0
+/dev/sda1 /mnt/My\\040Drive btrfs rw,relatime 0 0
+server:/export /home/me/nfs nfs4 rw,relatime,vers=4.2 0 0
+";
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parse_mounts_reads_proc_mounts_lines() {
+        let mounts = parse_mounts(MOUNTS_FIXTURE);
+        assert_eq!(mounts.len(), 6);
+        assert_eq!(mounts[2], (path::PathBuf::from("/"), "ext4".to_string()));
+        assert_eq!(
+            mounts[4],
+            (path::PathBuf::from("/mnt/My Drive"), "btrfs".to_string())
+        );
+        assert!(parse_mounts("truncated line\n\n").is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn filesystem_types_skips_other_filesystems() {
+        let dir = TempDir::new();
+        dir.file("app/Cargo.toml", "[package]");
+
+        let only = |fs_type: &str| ScanOptions {
+            filesystem_types: vec![fs_type.to_string()],
+            ..ScanOptions::default()
+        };
+        let projects = |options: &ScanOptions| {
+            scan_with_options(&dir.path(), options)
+                .filter_map(|p| p.ok())
+                .count()
+        };
+        assert_eq!(projects(&only("no-such-fs")), 0);
+
+        // whatever filesystem the temp dir is really on
+        let mounts = parse_mounts(&fs::read_to_string(PROC_MOUNTS).unwrap());
+        let temp = fs::canonicalize(dir.path()).unwrap();
+        let (_, fs_type) = mounts
+            .iter()
+            .filter(|(mount_point, _)| temp.starts_with(mount_point))
+            .max_by_key(|(mount_point, _)| mount_point.components().count())
+            .unwrap();
+        assert_eq!(projects(&only(fs_type)), 1);
+    }
+
+    /// A Unity project with 7 artifact dirs of `files` equally sized files each
+    fn unity_fixture(files: usize) -> TempDir {
+        let dir = TempDir::new();
+        dir.file("Assembly-CSharp.csproj", "");
+        for ad in PROJECT_UNITY_DIRS.iter() {
+            for i in 0..files {
+                dir.file(&format!("{}/sub/{}", ad, i), "0123456789");
+            }
+        }
+        dir
+    }
+
+    #[test]
+    fn size_parallel_matches_size() {
+        let dir = unity_fixture(5);
+        let project = Project::detect_in(dir.path()).unwrap();
+        assert!(matches!(project.project_type, ProjectType::Unity));
+        assert_eq!(project.size(), 7 * 5 * 10);
+        assert_eq!(project.size_parallel(), project.size());
+    }
+
+    #[test]
+    fn move_and_restore_artifacts() {
+        let dir = TempDir::new();
+        dir.file("app/package.json", "{}");
+        dir.file("app/node_modules/left-pad/index.js", "module.exports = 1");
+        let destination = dir.path().join("elsewhere");
+        let project = Project::detect_in(&dir.path().join("app")).unwrap();
+
+        let result = project.move_artifacts(&destination).unwrap();
+        let moved_to = destination.join("app/node_modules");
+        assert_eq!(
+            result.moved_paths,
+            [(dir.path().join("app/node_modules"), moved_to.clone())]
+        );
+        assert!(!dir.path().join("app/node_modules").exists());
+        assert!(moved_to.join("left-pad/index.js").is_file());
+
+        project.restore_artifacts(&destination).unwrap();
+        assert!(dir
+            .path()
+            .join("app/node_modules/left-pad/index.js")
+            .is_file());
+        assert!(!moved_to.exists());
+    }
+
+    #[test]
+    fn move_artifacts_refuses_to_overwrite() {
+        let dir = TempDir::new();
+        dir.file("app/package.json", "{}");
+        dir.file("app/node_modules/a", "a");
+        dir.file("elsewhere/app/node_modules/b", "b");
+        let project = Project::detect_in(&dir.path().join("app")).unwrap();
+
+        let errors = project
+            .move_artifacts(&dir.path().join("elsewhere"))
+            .unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].error.kind(), io::ErrorKind::AlreadyExists);
+        assert!(dir.path().join("app/node_modules/a").is_file());
+    }
+
+    #[test]
+    fn several_uproject_files_are_one_project() {
+        let dir = TempDir::new();
+        dir.file("Game.uproject", "{}");
+        dir.file("GameServer.uproject", "{}");
+        dir.file("GameEditor.uproject", "{}");
+        dir.file("Intermediate/a", "");
+
+        let projects: Vec<_> = scan(&dir.path()).filter_map(|p| p.ok()).collect();
+        assert_eq!(projects.len(), 1);
+        assert!(matches!(projects[0].project_type, ProjectType::Unreal));
+        assert_eq!(projects[0].path, dir.path());
+    }
+
+    #[test]
+    fn compute_size_during_scan_matches_size() {
+        let dir = TempDir::new();
+        dir.file("app/package.json", "{}");
+        dir.file("app/node_modules/a/index.js", "12345");
+        dir.file("app/node_modules/b/index.js", "123");
+        dir.file("lib/Cargo.toml", "[package]");
+
+        let options = ScanOptions {
+            compute_size_during_scan: true,
+            ..ScanOptions::default()
+        };
+        let projects: Vec<_> = scan_with_options(&dir.path(), &options)
+            .filter_map(|p| p.ok())
+            .collect();
+        assert_eq!(projects.len(), 2);
+        for project in &projects {
+            assert_eq!(project.pre_computed_size, Some(project.size()));
+        }
+
+        let mut unsized_projects = scan(&dir.path()).filter_map(|p| p.ok());
+        assert!(unsized_projects.all(|p| p.pre_computed_size.is_none()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn can_clean_and_verify_permissions_with_read_only_dir() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new();
+        dir.file("package.json", "{}");
+        let node_modules = dir.path().join("node_modules");
+        dir.file("node_modules/a/index.js", "");
+        let project = Project::detect_in(dir.path()).unwrap();
+        assert!(project.can_clean());
+
+        fs::set_permissions(&node_modules, fs::Permissions::from_mode(0o555)).unwrap();
+        // root can write anywhere, so there's nothing to check
+        if unsafe { libc::geteuid() } != 0 {
+            assert!(!project.can_clean());
+            let options = CleanOptions {
+                verify_permissions: true,
+                ..CleanOptions::default()
+            };
+            let errors = project.clean_with_options(&options).unwrap_err();
+            assert!(matches!(&errors[..], [CleanError::PermissionDenied(p)] if *p == node_modules));
+            assert!(node_modules.join("a/index.js").exists());
+        }
+        fs::set_permissions(&node_modules, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn merge_cargo_workspaces_folds_members_into_root() {
+        let dir = TempDir::new();
+        dir.file(
+            "ws/Cargo.toml",
+            "[workspace]\nmembers = [\"a\", \"b\"]\nexclude = [\"standalone\"]\n",
+        );
+        dir.file("ws/Cargo.lock", "");
+        dir.file("ws/target/debug/a", "");
+        dir.file("ws/a/Cargo.toml", "[package]");
+        dir.file("ws/b/Cargo.toml", "[package]");
+        dir.file("ws/standalone/Cargo.toml", "[package]");
+        dir.file("ws/standalone/target/debug/s", "");
+
+        let options = ScanOptions {
+            merge_cargo_workspaces: true,
+            ..ScanOptions::default()
+        };
+        let mut projects: Vec<_> = scan_with_options(&dir.path(), &options)
+            .filter_map(|p| p.ok())
+            .collect();
+        projects.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(projects.len(), 2);
+        assert!(matches!(
+            projects[0].project_type,
+            ProjectType::CargoWorkspace
+        ));
+        assert_eq!(projects[0].path, dir.path().join("ws"));
+        assert!(matches!(projects[1].project_type, ProjectType::Cargo));
+        assert_eq!(projects[1].path, dir.path().join("ws/standalone"));
+    }
+
+    #[test]
+    fn clean_keep_n_builds_keeps_newest_files() {
+        let dir = TempDir::new();
+        dir.file("Cargo.toml", "[package]");
+        let start = time::SystemTime::now() - time::Duration::from_secs(1000);
+        for i in 0..10 {
+            let path = dir.file(&format!("target/debug/build-{}", i), "0123456789");
+            set_modified(&path, start + time::Duration::from_secs(i * 10));
+        }
+
+        let project = Project::detect_in(dir.path()).unwrap();
+        let result = project.clean_keep_n_builds(3).unwrap();
+        assert_eq!(result.removed.len(), 7);
+        assert_eq!(result.bytes_freed, 70);
+
+        let mut left: Vec<_> = fs::read_dir(dir.path().join("target/debug"))
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        left.sort();
+        assert_eq!(left, ["build-7", "build-8", "build-9"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn clean_keep_n_builds_leaves_symlink_targets_alone() {
+        use std::os::unix::fs::symlink;
+
+        let dir = TempDir::new();
+        dir.file("shared/a", "a");
+        dir.file("shared/b", "b");
+        dir.file("inner/Cargo.toml", "[package]");
+        dir.file("inner/target/debug/.keep", "");
+        symlink(
+            dir.path().join("shared"),
+            dir.path().join("inner/target/debug/shared"),
+        )
+        .unwrap();
+        dir.file("linked/Cargo.toml", "[package]");
+        symlink(dir.path().join("shared"), dir.path().join("linked/target")).unwrap();
+
+        for name in ["inner", "linked"] {
+            let project = Project::detect_in(&dir.path().join(name)).unwrap();
+            project.clean_keep_n_builds(0).unwrap();
+        }
+        assert!(dir.path().join("shared/a").exists());
+        assert!(dir.path().join("shared/b").exists());
+    }
+
+    #[test]
+    fn prelude_exports_everything() {
+        // named rather than globbed, `super::*` would hide anything missing from the prelude
+        use crate::prelude::{
+            dir_size, pretty_size, scan, scan_with_options, MultiError, Project, ProjectSize,
+            ProjectType, Red, ScanOptions,
+        };
+
+        let dir = TempDir::new();
+        dir.file("Cargo.toml", "[package]");
+        dir.file("target/a", "123");
+
+        let found: MultiError<Red> = scan(&dir.path()).collect();
+        let options = ScanOptions::default();
+        assert_eq!(scan_with_options(&dir.path(), &options).count(), 1);
+        let projects: Vec<Project> = found.into_result().unwrap();
+        assert!(matches!(projects[0].project_type, ProjectType::Cargo));
+        let sizes: ProjectSize = projects[0].size_dirs();
+        assert_eq!(sizes.artifact_size, dir_size(&dir.path().join("target")));
+        assert_eq!(pretty_size(sizes.artifact_size), "3.0B");
+    }
+
+    #[test]
+    fn artifact_dirs_mtime_sorted_oldest_first() {
+        let dir = TempDir::new();
+        dir.file("Assembly-CSharp.csproj", "");
+        let now = time::SystemTime::now();
+        let day = time::Duration::from_secs(86_400);
+        for (ad, days) in [("Library", 3), ("Temp", 10), ("Obj", 1)] {
+            let newest = dir.file(&format!("{}/newest", ad), "");
+            set_modified(&newest, now - day * days);
+            let older = dir.file(&format!("{}/older", ad), "");
+            set_modified(&older, now - day * (days + 20));
+        }
+
+        let project = Project::detect_in(dir.path()).unwrap();
+        let sorted = project.artifact_dirs_mtime_sorted();
+        let names: Vec<_> = sorted
+            .iter()
+            .map(|(path, _)| path.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(names, ["Temp", "Library", "Obj"]);
+        assert_eq!(sorted[0].1, now - day * 10);
+    }
+
+    #[test]
+    fn case_insensitive_matches_uppercase_manifests() {
+        let dir = TempDir::new();
+        dir.file("rust/CARGO.TOML", "[package]");
+        dir.file("scala/BUILD.SBT", "");
+        dir.file("notebook/Analysis.IPYNB", "{}");
+
+        let found = |case_insensitive| {
+            let options = ScanOptions {
+                case_insensitive,
+                ..ScanOptions::default()
+            };
+            let mut types: Vec<_> = scan_with_options(&dir.path(), &options)
+                .filter_map(|p| p.ok())
+                .map(|p| p.type_name())
+                .collect();
+            types.sort_unstable();
+            types
+        };
+        assert_eq!(found(true), ["Cargo", "Jupyter", "SBT"]);
+        assert!(found(false).is_empty());
+    }
+
+    #[test]
+    fn nix_result_dir_is_removed() {
+        let dir = TempDir::new();
+        dir.file("shell.nix", "");
+        dir.file("result/bin/app", "");
+        dir.file(".direnv/flake-profile", "");
+
+        let project = Project::detect_in(dir.path()).unwrap();
+        assert!(matches!(project.project_type, ProjectType::Nix));
+        project
+            .clean_with_options(&CleanOptions::default())
+            .unwrap();
+        assert!(!dir.path().join("result").exists());
+        assert!(!dir.path().join(".direnv").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn nix_result_symlink_into_the_store_only_removes_the_link() {
+        let dir = TempDir::new();
+        dir.file("store/abc-app/bin/app", "app");
+        dir.file("flake/flake.nix", "{}");
+        std::os::unix::fs::symlink(
+            dir.path().join("store/abc-app"),
+            dir.path().join("flake/result"),
+        )
+        .unwrap();
+
+        let project = Project::detect_in(&dir.path().join("flake")).unwrap();
+        assert!(matches!(project.project_type, ProjectType::Nix));
+        project
+            .clean_with_options(&CleanOptions::default())
+            .unwrap();
+        assert!(fs::symlink_metadata(dir.path().join("flake/result")).is_err());
+        assert!(dir.path().join("store/abc-app/bin/app").exists());
+    }
+
+    #[test]
+    fn size_timeout_gives_up_on_large_trees() {
+        let dir = TempDir::new();
+        dir.file("package.json", "{}");
+        for i in 0..1000 {
+            dir.file(&format!("node_modules/dep-{}/index.js", i), "1");
+        }
+        let project = Project::detect_in(dir.path()).unwrap();
+
+        assert_eq!(project.size_timeout(time::Duration::from_nanos(1)), None);
+        assert_eq!(
+            project.size_timeout(time::Duration::from_secs(60)),
+            Some(project.size())
+        );
+    }
+
+    #[cfg(feature = "cli")]
+    #[test]
+    fn clap_args_round_trip_through_a_command() {
+        let command = || clap::Command::new("scan").args(ScanOptions::clap_args());
+        command().debug_assert();
+
+        let matches = command()
+            .try_get_matches_from([
+                "scan",
+                "--merge-cargo-workspaces",
+                "--compute-size",
+                "--case-sensitive",
+            ])
+            .unwrap();
+        let options = ScanOptions::from_clap_matches(&matches);
+        assert!(options.merge_cargo_workspaces);
+        assert!(options.compute_size_during_scan);
+        assert!(!options.case_insensitive);
+        assert!(!options.load_metadata);
+
+        let defaults = ScanOptions::from_clap_matches(&command().get_matches_from(["scan"]));
+        assert!(!defaults.merge_cargo_workspaces);
+        assert_eq!(defaults.case_insensitive, CASE_INSENSITIVE_FS);
+
+        assert!(command()
+            .try_get_matches_from(["scan", "--case-sensitive", "--case-insensitive"])
+            .is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn broken_symlinks_are_found_and_removed() {
+        use std::os::unix::fs::symlink;
+
+        let dir = TempDir::new();
+        dir.file("Cargo.toml", "[package]");
+        let target = dir.file("target/debug/app", "app");
+        symlink(&target, dir.path().join("target/debug/app-link")).unwrap();
+        let broken = dir.path().join("target/debug/dangling");
+        symlink(dir.path().join("gone"), &broken).unwrap();
+
+        let project = Project::detect_in(dir.path()).unwrap();
+        assert_eq!(project.symlink_count(), 2);
+        assert_eq!(
+            project.broken_symlink_paths(),
+            std::slice::from_ref(&broken)
+        );
+
+        let options = CleanOptions {
+            remove_broken_symlinks: true,
+            ..CleanOptions::default()
+        };
+        let result = project.clean_with_options(&options).unwrap();
+        assert_eq!(result.removed[0], broken);
+        assert!(!dir.path().join("target").exists());
+    }
+
+    #[test]
+    fn artifact_file_extensions_sums_sizes_per_extension() {
+        let dir = TempDir::new();
+        dir.file("Cargo.toml", "[package]");
+        dir.file("target/debug/deps/libfoo.rlib", "0123456789");
+        dir.file("target/debug/deps/libbar.rlib", "01234");
+        dir.file("target/debug/deps/foo.d", "12");
+        dir.file("target/debug/foo.o", "123");
+        dir.file("target/debug/app", "1234");
+
+        let extensions = Project::detect_in(dir.path())
+            .unwrap()
+            .artifact_file_extensions();
+        let expected: HashMap<String, u64> = [("rlib", 15), ("d", 2), ("o", 3), ("", 4)]
+            .iter()
+            .map(|&(ext, size)| (ext.to_string(), size))
+            .collect();
+        assert_eq!(extensions, expected);
+    }
+
+    #[test]
+    fn clean_all_with_stats_sums_the_batch() {
+        let dir = TempDir::new();
+        dir.file("a/Cargo.toml", "[package]");
+        dir.file("a/target/debug/a", "0123456789");
+        dir.file("b/package.json", "{}");
+        dir.file("b/node_modules/b/index.js", "01234");
+        dir.file("c/pom.xml", "");
+        let projects: Vec<_> = scan(&dir.path()).filter_map(|p| p.ok()).collect();
+        assert_eq!(projects.len(), 3);
+
+        let report = clean_all_with_stats(&projects);
+        assert_eq!(report.projects_cleaned, 3);
+        assert_eq!(report.projects_failed, 0);
+        assert!(report.errors.is_empty());
+        assert_eq!(report.total_bytes_freed, 15);
+        assert_eq!(
+            report.display(),
+            "cleaned 3 projects, freed 15.0B, 0 errors"
+        );
+        assert!(!dir.path().join("a/target").exists());
+        assert!(!dir.path().join("b/node_modules").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn scan_new_since_only_yields_newer_projects() {
+        let dir = TempDir::new();
+        dir.file("old/Cargo.toml", "[package]");
+        let since = time::SystemTime::now() - time::Duration::from_secs(3600);
+        // directories can't be opened for writing, but their owner can still set times
+        fs::File::open(dir.path().join("old"))
+            .unwrap()
+            .set_modified(since - time::Duration::from_secs(3600))
+            .unwrap();
+        dir.file("new/package.json", "{}");
+        dir.file("nested/deep/new/pom.xml", "");
+
+        let mut found: Vec<_> = scan_new_since(&dir.path(), since)
+            .filter_map(|p| p.ok())
+            .map(|p| p.path)
+            .collect();
+        found.sort();
+        assert_eq!(
+            found,
+            [dir.path().join("nested/deep/new"), dir.path().join("new")]
+        );
+    }
+
+    #[test]
+    fn size_value_units_and_ordering() {
+        let small = SizeValue::new(1536);
+        assert_eq!(small.display_iec(), "1.5KiB");
+        assert_eq!(small.display_si(), "1.5kB");
+        assert_eq!(SizeValue::new(999).display_si(), "999.0B");
+        assert_eq!(SizeValue::new(2_500_000).display_si(), "2.5MB");
+
+        let big = SizeValue::new(1_000_000);
+        assert!(small < big);
+        assert_eq!(small, SizeValue::new(1536));
+
+        let dir = TempDir::new();
+        dir.file("package.json", "{}");
+        dir.file("node_modules/a", "12345");
+        let size = Project::detect_in(dir.path()).unwrap().size_with_units();
+        assert_eq!(size.bytes, 5);
+        assert_eq!(size.pretty, pretty_size(5));
+    }
+
+    #[test]
+    fn detect_in_ancestors_finds_member_then_workspace() {
+        let dir = TempDir::new();
+        dir.file("ws/Cargo.toml", "[workspace]\nmembers = [\"crates/app\"]\n");
+        dir.file("ws/crates/app/Cargo.toml", "[package]");
+        let src = dir.file("ws/crates/app/src/deep/mod.rs", "");
+        let start = src.parent().unwrap();
+
+        let nearest = Project::detect_in_ancestors(start).unwrap();
+        assert_eq!(nearest.path, dir.path().join("ws/crates/app"));
+
+        // stop at the fixture, anything above it isn't ours
+        let all: Vec<_> = Project::detect_all_ancestors(start)
+            .into_iter()
+            .map(|p| p.path)
+            .filter(|p| p.starts_with(dir.path()))
+            .collect();
+        assert_eq!(
+            all,
+            [dir.path().join("ws/crates/app"), dir.path().join("ws")]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dir_size_dedup_counts_hard_links_once() {
+        let dir = TempDir::new();
+        let original = dir.file("store/blob", "0123456789");
+        fs::hard_link(&original, dir.path().join("store/link-1")).unwrap();
+        fs::create_dir_all(dir.path().join("store/sub")).unwrap();
+        fs::hard_link(&original, dir.path().join("store/sub/link-2")).unwrap();
+        dir.file("store/other", "12345");
+
+        let store = dir.path().join("store");
+        assert_eq!(dir_size(&store), 35);
+        assert_eq!(dir_size_dedup(&store), 15);
+    }
+
+    #[test]
+    fn red_network_filesystem_into_io_error() {
+        let red = Red::NetworkFilesystem {
+            path: "/mnt/share".into(),
+            fs_type: "nfs".to_string(),
+        };
+        assert!(red.source().is_none());
+        let error = io::Error::from(red);
+        assert!(error.to_string().contains("/mnt/share"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn network_fs_type_in_uses_the_innermost_mount() {
+        let mounts = parse_mounts(MOUNTS_FIXTURE);
+        let fs_type = |path: &str| network_fs_type_in(&mounts, path::Path::new(path));
+        assert_eq!(fs_type("/home/me/nfs"), Some("nfs4".to_string()));
+        assert_eq!(
+            fs_type("/home/me/nfs/project/src"),
+            Some("nfs4".to_string())
+        );
+        assert_eq!(fs_type("/home/me/nfsish"), None);
+        assert_eq!(fs_type("/home/me"), None);
+        assert_eq!(fs_type("/mnt/My Drive/code"), None);
+
+        let mut mounts = mounts;
+        mounts.push(("/home/me/nfs/local".into(), "ext4".to_string()));
+        assert_eq!(
+            network_fs_type_in(&mounts, path::Path::new("/home/me/nfs/local/a")),
+            None
+        );
+    }
+
+    #[test]
+    fn local_scan_root_gets_no_network_warning() {
+        let dir = TempDir::new();
+        dir.file("app/Cargo.toml", "[package]");
+        let results: Vec<_> = scan_with_options(&dir.path(), &ScanOptions::default()).collect();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].is_ok());
+        assert!(network_warning(dir.path(), &ScanOptions::default()).is_none());
+    }
+
+    #[test]
+    fn rye_detected_by_lockfile_and_pyproject_section() {
+        let dir = TempDir::new();
+        dir.file("lock/rye.lock", "");
+        dir.file("lock/main.py", "");
+        dir.file("lock/.venv/bin/python", "");
+        dir.file("lock/dist/app.whl", "");
+        dir.file("section/pyproject.toml", "[tool.rye]\nmanaged = true\n");
+
+        let lock = Project::detect_in(&dir.path().join("lock")).unwrap();
+        assert!(matches!(lock.project_type, ProjectType::Rye));
+        assert_eq!(lock.type_name(), "Rye");
+        let section = Project::detect_in(&dir.path().join("section")).unwrap();
+        assert!(matches!(section.project_type, ProjectType::Rye));
+
+        lock.clean_with_options(&CleanOptions::default()).unwrap();
+        assert!(!dir.path().join("lock/.venv").exists());
+        assert!(!dir.path().join("lock/dist").exists());
+        assert!(dir.path().join("lock/rye.lock").exists());
+    }
+
+    const ALL_PROJECT_TYPES: [ProjectType; 21] = [
+        ProjectType::Cargo,
+        ProjectType::CargoWorkspace,
+        ProjectType::Node,
+        ProjectType::Unity,
+        ProjectType::Stack,
+        ProjectType::SBT,
+        ProjectType::Maven,
+        ProjectType::CMake,
+        ProjectType::Unreal,
+        ProjectType::Jupyter,
+        ProjectType::Python,
+        ProjectType::Composer,
+        ProjectType::Buck,
+        ProjectType::Pants,
+        ProjectType::Poetry,
+        ProjectType::PDM,
+        ProjectType::Hatch,
+        ProjectType::Nix,
+        ProjectType::Rye,
+        ProjectType::Nx,
+        ProjectType::Turborepo,
+    ];
+
+    #[test]
+    fn every_project_type_has_icons() {
+        for project_type in ALL_PROJECT_TYPES {
+            let project = Project::new(project_type, "project".into());
+            assert!(!project.type_icon().is_empty(), "{}", project.type_name());
+            assert!(
+                !project.type_icon_ascii().is_empty(),
+                "{}",
+                project.type_name()
+            );
+            assert!(
+                project.type_icon_ascii().is_ascii(),
+                "{}",
+                project.type_name()
+            );
+        }
+        assert_eq!(project_at("a").type_icon(), "🦀");
+    }
+
+    #[cfg(feature = "test-helpers")]
+    #[test]
+    fn touch_artifact_dirs_sets_every_mtime() {
+        let dir = TempDir::new();
+        dir.file("Cargo.toml", "[package]");
+        dir.file("target/debug/a", "");
+        dir.file("target/b", "");
+        let project = Project::detect_in(dir.path()).unwrap();
+
+        let then = time::SystemTime::now() - time::Duration::from_secs(50 * 86_400);
+        project.touch_artifact_dirs(then).unwrap();
+        assert_eq!(dir_age(&dir.path().join("target")), Some(then));
+        assert_eq!(project.artifact_dirs_mtime_sorted()[0].1, then);
+    }
+
+    #[test]
+    fn multi_error_partition_and_into_projects() {
+        let mixed = || {
+            multi_error(vec![
+                Ok(project_at("a")),
+                Err(io::Error::new(io::ErrorKind::NotFound, "first")),
+                Ok(project_at("b")),
+                Err(io::Error::other("second")),
+            ])
+        };
+
+        let (projects, errors) = mixed().partition();
+        assert_eq!(projects.len(), 2);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[1].to_string(), "second");
+
+        let kept = mixed().into_projects().unwrap_err();
+        assert_eq!(kept.success().len(), 2);
+        assert_eq!(kept.error_count(), 2);
+
+        let ok = multi_error(vec![Ok(project_at("a"))]);
+        assert_eq!(ok.into_projects().unwrap().len(), 1);
+        let (projects, errors) = multi_error(vec![]).partition();
+        assert!(projects.is_empty() && errors.is_empty());
+    }
+
+    #[test]
+    fn kondo_exclude_paths_are_left_out_of_scan() {
+        let dir = TempDir::new();
+        dir.file("keep/Cargo.toml", "[package]");
+        dir.file("vendor/a/Cargo.toml", "[package]");
+        dir.file("archive/package.json", "{}");
+
+        // only paths inside this test's fixture, so other tests scanning concurrently are unaffected
+        let excluded =
+            std::env::join_paths([dir.path().join("vendor"), dir.path().join("archive")]).unwrap();
+        std::env::set_var(ENV_KONDO_EXCLUDE, &excluded);
+        let options = ScanOptions::from_env();
+        let found: Vec<_> = scan(&dir.path())
+            .filter_map(|p| p.ok())
+            .map(|p| p.path)
+            .collect();
+        std::env::remove_var(ENV_KONDO_EXCLUDE);
+
+        assert_eq!(options.exclude_paths.len(), 2);
+        assert_eq!(found, [dir.path().join("keep")]);
+    }
+
+    #[test]
+    fn size_breakdown_by_extension_is_sorted_biggest_first() {
+        let dir = TempDir::new();
+        dir.file("Cargo.toml", "[package]");
+        dir.file("target/debug/deps/a.rlib", "0123456789");
+        dir.file("target/debug/deps/b.rlib", "0123456789");
+        dir.file("target/debug/deps/a.d", "1");
+        dir.file("target/debug/build/out.o", "12345");
+        dir.file("target/debug/app", "123");
+
+        let breakdown = Project::detect_in(dir.path())
+            .unwrap()
+            .size_breakdown_by_extension();
+        let expected = [("rlib", 20), ("o", 5), ("", 3), ("d", 1)];
+        let expected: Vec<_> = expected
+            .iter()
+            .map(|&(ext, size)| (ext.to_string(), size))
+            .collect();
+        assert_eq!(breakdown, expected);
+    }
+
+    #[test]
+    fn scan_events_always_end_with_scan_complete() {
+        let dir = TempDir::new();
+        dir.file("a/Cargo.toml", "[package]");
+        dir.file("b/package.json", "{}");
+        dir.file("c/README.md", "");
+
+        let events: Vec<_> = scan_events(&dir.path()).collect();
+        let entered = events
+            .iter()
+            .filter(|e| matches!(e, ProjectEvent::DirectoryEntered(_)))
+            .count() as u64;
+        let found = events
+            .iter()
+            .filter(|e| matches!(e, ProjectEvent::ProjectFound(_)))
+            .count() as u64;
+        assert_eq!(found, 2);
+        assert!(entered >= 4);
+        match events.last() {
+            Some(ProjectEvent::ScanComplete {
+                total_dirs,
+                total_projects,
+            }) => assert_eq!((*total_dirs, *total_projects), (entered, found)),
+            e => panic!("expected ScanComplete last, got {:?}", e),
+        }
+
+        let missing: Vec<_> = scan_events(&dir.path().join("missing")).collect();
+        assert!(matches!(missing[0], ProjectEvent::Error(_)));
+        assert!(matches!(
+            missing.last(),
+            Some(ProjectEvent::ScanComplete {
+                total_dirs: 0,
+                total_projects: 0
+            })
+        ));
+    }
+
+    #[test]
+    fn clean_order_decides_removal_order() {
+        let removed_order = |order: Option<CleanOrder>| {
+            let dir = TempDir::new();
+            dir.file("Assembly-CSharp.csproj", "");
+            dir.file("Library/a", "01234");
+            dir.file("Temp/a", "0123456789");
+            dir.file("Logs/a", "0");
+            let project = Project::detect_in(dir.path()).unwrap();
+            let result = match order {
+                None => project.clean_by_priority(),
+                Some(priority_order) => project.clean_with_options(&CleanOptions {
+                    priority_order,
+                    ..CleanOptions::default()
+                }),
+            }
+            .unwrap();
+            // only the orders that sort by size measure anything
+            let measured = matches!(
+                order,
+                None | Some(CleanOrder::LargestFirst | CleanOrder::SmallestFirst)
+            );
+            assert_eq!(result.bytes_freed, if measured { 16 } else { 0 });
+            result
+                .removed
+                .iter()
+                .map(|p| p.file_name().unwrap().to_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(removed_order(None), ["Temp", "Library", "Logs"]);
+        assert_eq!(
+            removed_order(Some(CleanOrder::LargestFirst)),
+            ["Temp", "Library", "Logs"]
+        );
+        assert_eq!(
+            removed_order(Some(CleanOrder::SmallestFirst)),
+            ["Logs", "Library", "Temp"]
+        );
+        assert_eq!(
+            removed_order(Some(CleanOrder::Alphabetical)),
+            ["Library", "Logs", "Temp"]
+        );
+        // the order of PROJECT_UNITY_DIRS
+        assert_eq!(
+            removed_order(Some(CleanOrder::Default)),
+            ["Library", "Temp", "Logs"]
+        );
+
+        let dir = TempDir::new();
+        dir.file("Assembly-CSharp.csproj", "");
+        dir.file("Temp/a", "0123456789");
+        let result = Project::detect_in(dir.path())
+            .unwrap()
+            .clean_with_options(&CleanOptions {
+                measure_sizes: true,
+                ..CleanOptions::default()
+            })
+            .unwrap();
+        assert_eq!(result.bytes_freed, 10);
+    }
+
+    #[test]
+    fn project_builder_validates_fields() {
+        let dir = TempDir::new();
+        let project = ProjectBuilder::new()
+            .path(dir.path())
+            .project_type(ProjectType::Maven)
+            .build()
+            .unwrap();
+        assert_eq!(project.path, dir.path());
+        assert!(matches!(project.project_type, ProjectType::Maven));
+
+        let missing_path = ProjectBuilder::new()
+            .project_type(ProjectType::Node)
+            .build();
+        assert!(matches!(missing_path, Err(BuildError::MissingPath)));
+        let missing_type = ProjectBuilder::new().path(dir.path()).build();
+        assert!(matches!(missing_type, Err(BuildError::MissingProjectType)));
+        let gone = dir.path().join("gone");
+        match ProjectBuilder::new()
+            .path(&gone)
+            .project_type(ProjectType::Node)
+            .build()
+        {
+            Err(BuildError::PathNotFound(path)) => assert_eq!(path, gone),
+            r => panic!("expected PathNotFound, got {:?}", r),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dir_disk_usage_rounds_small_files_up_to_blocks() {
+        let dir = TempDir::new();
+        for i in 0..10 {
+            dir.file(&format!("small/{}", i), "1");
+        }
+        let small = dir.path().join("small");
+        assert_eq!(dir_size(&small), 10);
+        assert!(dir_disk_usage(&small) > dir_size(&small));
+        assert_eq!(dir_disk_usage(&small) % 512, 0);
+    }
+
+    #[test]
+    fn injected_artifact_dir_names_are_rejected() {
+        for name in [
+            "../outside",
+            "target/../../outside",
+            "/etc",
+            "",
+            ".",
+            "tar\0get",
+        ] {
+            assert!(!is_contained_artifact_dir(name), "{:?}", name);
+        }
+        #[cfg(windows)]
+        assert!(!is_contained_artifact_dir("C:\\Windows"));
+        for name in ["target", "project/target", ".venv", "node_modules"] {
+            assert!(is_contained_artifact_dir(name), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn artifact_dirs_verified_keeps_builtin_dirs() {
+        for project_type in ALL_PROJECT_TYPES {
+            let project = Project::new(project_type, "project".into());
+            assert_eq!(project.artifact_dirs_verified(), project.artifact_dirs());
+        }
+    }
+
+    #[test]
+    fn artifact_hash_is_stable_until_artifacts_change() {
+        let dir = TempDir::new();
+        dir.file("app/Cargo.toml", "[package]");
+        dir.file("app/target/debug/app", "app");
+        let options = ScanOptions {
+            compute_artifact_hash: true,
+            ..ScanOptions::default()
+        };
+        let hash = || {
+            let projects: Vec<_> = scan_with_options(&dir.path(), &options)
+                .filter_map(|p| p.ok())
+                .collect();
+            assert_eq!(projects.len(), 1);
+            projects[0].artifact_dir_hash.unwrap()
+        };
+
+        let first = hash();
+        assert_eq!(hash(), first);
+        dir.file("app/target/debug/new", "");
+        assert_ne!(hash(), first);
+        assert!(scan(&dir.path()).all(|p| p.unwrap().artifact_dir_hash.is_none()));
+    }
+
+    #[test]
+    fn clean_with_callback_reports_each_dir() {
+        let dir = TempDir::new();
+        dir.file("build.sbt", "");
+        dir.file("target/a", "0123456789");
+        dir.file("project/target/b", "01234");
+        let project = Project::detect_in(dir.path()).unwrap();
+
+        let events = std::cell::RefCell::new(Vec::new());
+        let result = project
+            .clean_with_callback(|path, event| {
+                let name = path.strip_prefix(dir.path()).unwrap().to_path_buf();
+                events.borrow_mut().push((name, format!("{:?}", event)));
+            })
+            .unwrap();
+        assert_eq!(result.bytes_freed, 15);
+        let target = path::PathBuf::from("target");
+        let project_target = path::PathBuf::from("project/target");
+        assert_eq!(
+            events.into_inner(),
+            [
+                (target.clone(), "BeforeRemove { size: 10 }".to_string()),
+                (target, "AfterRemove { bytes_freed: 10 }".to_string()),
+                (
+                    project_target.clone(),
+                    "BeforeRemove { size: 5 }".to_string()
+                ),
+                (project_target, "AfterRemove { bytes_freed: 5 }".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn scan_to_dot_writes_nodes_and_nesting_edges() {
+        let dir = TempDir::new();
+        dir.file("outer/Cargo.toml", "[package]");
+        dir.file("outer/target/a", "123");
+        dir.file("outer/web/package.json", "{}");
+        let outer = dir.path().join("outer");
+        let web = dir.path().join("outer/web");
+
+        let mut out = Vec::new();
+        scan_to_dot(&dir.path(), &mut out).unwrap();
+        let dot = String::from_utf8(out).unwrap();
+        assert!(dot.starts_with("digraph {\n"), "{}", dot);
+        assert!(dot.ends_with("}\n"), "{}", dot);
+        assert!(dot.contains("rankdir=\"TB\";"), "{}", dot);
+
+        let node = |path: &path::Path, label: &str| {
+            let label = format!("[label=\"{}\\n{}\"];", path.display(), label);
+            let line = dot.lines().find(|l| l.ends_with(&label)).unwrap();
+            line.trim().split(' ').next().unwrap().to_string()
+        };
+        let outer_node = node(&outer, "Cargo\\n3.0B");
+        let web_node = node(&web, "Node\\n0.0B");
+        assert!(
+            dot.contains(&format!("    {} -> {};\n", outer_node, web_node)),
+            "{}",
+            dot
+        );
+        assert_eq!(dot.matches("->").count(), 1);
+
+        let mut out = Vec::new();
+        let options = DotOptions {
+            show_sizes: false,
+            show_types: false,
+            rankdir: "LR".to_string(),
+        };
+        scan_to_dot_with_options(&dir.path(), &mut out, &options).unwrap();
+        let dot = String::from_utf8(out).unwrap();
+        assert!(dot.contains("rankdir=\"LR\";"));
+        assert!(dot.contains(&format!("[label=\"{}\"];", web.display())));
+    }
+
+    #[test]
+    fn artifact_dirs_sizes_only_has_artifact_dirs() {
+        let dir = TempDir::new();
+        dir.file("build.sbt", "");
+        dir.file("target/a", "0123456789");
+        dir.file("project/target/b", "01234");
+        dir.file("project/build.properties", "sbt.version=1");
+        dir.file("src/main.scala", "object Main");
+
+        let sizes = Project::detect_in(dir.path())
+            .unwrap()
+            .artifact_dirs_sizes();
+        let expected: HashMap<String, u64> = [("target", 10), ("project/target", 5)]
+            .iter()
+            .map(|&(ad, size)| (ad.to_string(), size))
+            .collect();
+        assert_eq!(sizes, expected);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn deduplicate_drops_projects_reached_through_symlinks() {
+        let dir = TempDir::new();
+        dir.file("real/Cargo.toml", "[package]");
+        dir.file("other/package.json", "{}");
+        std::os::unix::fs::symlink(dir.path().join("real"), dir.path().join("link")).unwrap();
+
+        let real = Project::detect_in(&dir.path().join("real")).unwrap();
+        let link = Project::detect_in(&dir.path().join("link")).unwrap();
+        let other = Project::detect_in(&dir.path().join("other")).unwrap();
+        let kept: Vec<_> = deduplicate_projects(vec![link, other, real])
+            .into_iter()
+            .map(|p| p.path)
+            .collect();
+        assert_eq!(kept, [dir.path().join("link"), dir.path().join("other")]);
+
+        let count = |deduplicate| {
+            let options = ScanOptions {
+                deduplicate,
+                ..ScanOptions::default()
+            };
+            scan_with_options(&dir.path(), &options)
+                .filter_map(|p| p.ok())
+                .count()
+        };
+        assert_eq!(count(false), 3);
+        assert_eq!(count(true), 2);
+    }
+
+    #[test]
+    fn clean_log_round_trip() {
+        let dir = TempDir::new();
+        dir.file("a \"quoted\"/Cargo.toml", "[package]");
+        dir.file("a \"quoted\"/target/a", "0123456789");
+        dir.file("b/package.json", "{}");
+        dir.file("b/node_modules/b", "01234");
+        let log = dir.path().join("clean.log");
+
+        let before = time::SystemTime::now() - time::Duration::from_secs(1);
+        for name in ["a \"quoted\"", "b"] {
+            let project = Project::detect_in(&dir.path().join(name)).unwrap();
+            project.clean_with_log(&log).unwrap();
+        }
+
+        let entries = read_clean_log(&log).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].project_path, dir.path().join("a \"quoted\""));
+        assert_eq!(
+            entries[0].artifact_dir,
+            dir.path().join("a \"quoted\"/target")
+        );
+        assert_eq!(entries[0].bytes_freed, 10);
+        assert_eq!(entries[1].project_path, dir.path().join("b"));
+        assert_eq!(entries[1].bytes_freed, 5);
+        assert!(entries.iter().all(|e| e.timestamp >= before));
+
+        fs::write(&log, "{\"timestamp\":1}\n").unwrap();
+        assert_eq!(
+            read_clean_log(&log).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn scan_with_detector_recognises_a_custom_marker() {
+        let dir = TempDir::new();
+        dir.file("custom/.kondo-cmake", "");
+        dir.file("custom/README.md", "");
+        dir.file("both/.kondo-cmake", "");
+        dir.file("both/package.json", "{}");
+        let detector = |file_name: &str| match file_name {
+            ".kondo-cmake" => Some(ProjectType::CMake),
+            _ => None,
+        };
+        let found = |mode| {
+            let mut found: Vec<_> = scan_with_detector(&dir.path(), detector, mode)
+                .filter_map(|p| p.ok())
+                .map(|p| (p.path.clone(), p.type_name()))
+                .collect();
+            found.sort();
+            found
+        };
+
+        let both = dir.path().join("both");
+        let custom = dir.path().join("custom");
+        assert_eq!(
+            found(DetectorMode::Fallback),
+            [(both.clone(), "Node"), (custom.clone(), "CMake")]
+        );
+        assert_eq!(
+            found(DetectorMode::Override),
+            [(both, "CMake"), (custom, "CMake")]
+        );
+    }
+
+    /// Polls `future` to completion, fine for futures that never actually wait
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        let mut future = Box::pin(future);
+        let mut context = std::task::Context::from_waker(std::task::Waker::noop());
+        loop {
+            if let std::task::Poll::Ready(output) = future.as_mut().poll(&mut context) {
+                return output;
+            }
+        }
+    }
+
+    #[test]
+    fn size_diff_of_a_clean_is_minus_the_artifact_size() {
+        let dir = TempDir::new();
+        dir.file("package.json", "{}");
+        dir.file("node_modules/a", "0123456789");
+        let project = Project::detect_in(dir.path()).unwrap();
+
+        let diff = project.size_diff(|| {
+            project
+                .clean_with_options(&CleanOptions::default())
+                .unwrap();
+        });
+        assert_eq!(diff, -10);
+
+        dir.file("node_modules/b", "01234");
+        let diff = block_on(project.size_diff_async(async {
+            fs::remove_file(dir.path().join("node_modules/b")).unwrap();
+        }));
+        assert_eq!(diff, -5);
+        assert_eq!(project.size_diff(|| {}), 0);
+    }
+
+    #[test]
+    fn merge_and_subtract_scan_results() {
+        let dir = TempDir::new();
+        dir.file("home/a/Cargo.toml", "[package]");
+        dir.file("home/projects/b/package.json", "{}");
+        dir.file("home/projects/c/pom.xml", "");
+        let paths = |projects: &[Project]| -> Vec<path::PathBuf> {
+            projects.iter().map(|p| p.path.clone()).collect()
+        };
+        let home: Vec<_> = scan(&dir.path().join("home"))
+            .filter_map(|p| p.ok())
+            .collect();
+        let projects: Vec<_> = scan(&dir.path().join("home/projects"))
+            .filter_map(|p| p.ok())
+            .collect();
+
+        let merged = merge_scan_results(home.clone(), projects.clone());
+        assert_eq!(
+            paths(&merged),
+            [
+                dir.path().join("home/a"),
+                dir.path().join("home/projects/b"),
+                dir.path().join("home/projects/c"),
+            ]
+        );
+        // the same project reached through a path that isn't canonical
+        let dotted = merge_scan_results(
+            projects.clone(),
+            vec![Project::detect_in(&dir.path().join("home/a/../projects/b")).unwrap()],
+        );
+        assert_eq!(dotted.len(), 2);
+
+        let subtracted = subtract_scan_results(home, projects);
+        assert_eq!(paths(&subtracted), [dir.path().join("home/a")]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn artifact_dirs_symlink_targets_resolves_links() {
+        let dir = TempDir::new();
+        dir.file("store/abc-app/bin/app", "");
+        dir.file("flake/flake.nix", "{}");
+        dir.file("flake/.direnv/profile", "");
+        let store = dir.path().join("store/abc-app");
+        std::os::unix::fs::symlink(&store, dir.path().join("flake/result")).unwrap();
+
+        let project = Project::detect_in(&dir.path().join("flake")).unwrap();
+        assert!(project.artifact_dirs_are_symlinks());
+        assert_eq!(
+            project.artifact_dirs_symlink_targets(),
+            [
+                (dir.path().join("flake/result"), Some(store)),
+                (dir.path().join("flake/.direnv"), None),
+            ]
+        );
+
+        fs::remove_file(dir.path().join("flake/result")).unwrap();
+        assert!(!project.artifact_dirs_are_symlinks());
+    }
+
+    #[test]
+    fn skip_dirs_are_not_entered() {
+        let dir = TempDir::new();
+        dir.file("AppData/Local/app/package.json", "{}");
+        dir.file("mirror/crate/Cargo.toml", "[package]");
+        dir.file("code/app/Cargo.toml", "[package]");
+
+        let found = |options: &ScanOptions| {
+            let mut found: Vec<_> = scan_with_options(&dir.path(), options)
+                .filter_map(|p| p.ok())
+                .map(|p| p.path)
+                .collect();
+            found.sort();
+            found
+        };
+        let code = dir.path().join("code/app");
+        let mirror = dir.path().join("mirror/crate");
+        let app_data = dir.path().join("AppData/Local/app");
+
+        assert_eq!(found(&ScanOptions::default()), [code.clone(), mirror]);
+        let skipping = ScanOptions {
+            additional_skip_dirs: vec!["mirror".to_string()],
+            ..ScanOptions::default()
+        };
+        assert_eq!(found(&skipping), std::slice::from_ref(&code));
+        let no_builtin = ScanOptions {
+            builtin_skip_dirs: false,
+            ..skipping
+        };
+        assert_eq!(found(&no_builtin), [app_data, code]);
+    }
+
+    #[test]
+    fn nx_wins_over_package_json_and_cleans_node_modules() {
+        let dir = TempDir::new();
+        dir.file("nx.json", "{}");
+        dir.file("package.json", "{}");
+        dir.file("node_modules/left-pad/index.js", "module.exports = 1");
+        dir.file(".nx/cache/a", "a");
+
+        let project = Project::detect_in(dir.path()).unwrap();
+        assert!(matches!(project.project_type, ProjectType::Nx));
+        assert!(project.artifact_dirs().contains(&"node_modules"));
+        assert!(project.artifact_dirs().contains(&".nx"));
+
+        project
+            .clean_with_options(&CleanOptions::default())
+            .unwrap();
+        assert!(!dir.path().join("node_modules").exists());
+        assert!(!dir.path().join(".nx").exists());
+    }
+
+    #[test]
+    fn package_json_beside_other_manifests_still_counts_as_node() {
+        let dir = TempDir::new();
+        dir.file("package.json", "{}");
+        dir.file("main.py", "");
+        let project = Project::detect_in(dir.path()).unwrap();
+        assert!(matches!(project.project_type, ProjectType::Node));
+    }
+
+    #[test]
+    fn clean_incremental_only_keeps_rest_of_target() {
+        let dir = TempDir::new();
+        dir.file("Cargo.toml", "[package]");
+        dir.file("target/debug/incremental/app-1/dep-graph.bin", "0123456789");
+        dir.file("target/release/incremental/app-2/query-cache.bin", "01234");
+        let binary = dir.file("target/debug/app", "binary");
+        let project = Project::new(ProjectType::Cargo, dir.path().into());
+
+        let mut incremental = project.incremental_dirs();
+        incremental.sort();
+        assert_eq!(
+            incremental,
+            [
+                dir.path().join("target/debug/incremental"),
+                dir.path().join("target/release/incremental"),
+            ]
+        );
+
+        let result = project.clean_incremental_only().unwrap();
+        assert_eq!(result.removed.len(), 2);
+        assert_eq!(result.bytes_freed, 15);
+        assert!(project.incremental_dirs().is_empty());
+        assert!(binary.is_file());
+        assert!(dir.path().join("target/release").is_dir());
+
+        let node = Project::new(ProjectType::Node, dir.path().into());
+        assert!(node.incremental_dirs().is_empty());
+    }
+
+    #[test]
+    fn size_excluding_skips_named_subdirs() {
+        let dir = TempDir::new();
+        dir.file("Assembly-CSharp.csproj", "");
+        dir.file(
+            "Library/PackageCache/com.unity.ugui/package.json",
+            "0123456789",
+        );
+        dir.file("Library/ScriptAssemblies/Game.dll", "01234");
+        dir.file("Temp/PackageCache/lock", "012");
+        let project = Project::new(ProjectType::Unity, dir.path().into());
+
+        assert_eq!(project.size(), 18);
+        assert_eq!(project.size_excluding(&["PackageCache"]), 5);
+        assert_eq!(project.size_excluding(&[]), 18);
+    }
+
+    #[test]
+    fn metadata_round_trip() {
+        let dir = TempDir::new();
+        dir.file("Cargo.toml", "[package]");
+        let cleaned = time::UNIX_EPOCH + time::Duration::from_secs(1_700_000_000);
+        let mut project = Project::new(ProjectType::Cargo, dir.path().into());
+        project.last_cleaned = Some(cleaned);
+        project.save_metadata().unwrap();
+
+        let mut loaded = Project::new(ProjectType::Cargo, dir.path().into());
+        loaded.load_metadata().unwrap();
+        assert_eq!(loaded.last_cleaned, Some(cleaned));
+
+        let last_cleaned = |load_metadata| {
+            let options = ScanOptions {
+                load_metadata,
+                ..ScanOptions::default()
+            };
+            scan_with_options(&dir.path(), &options)
+                .filter_map(|p| p.ok())
+                .map(|p| p.last_cleaned)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(last_cleaned(true), [Some(cleaned)]);
+        assert_eq!(last_cleaned(false), [None]);
+    }
+
+    #[test]
+    fn load_metadata_without_file_errors() {
+        let dir = TempDir::new();
+        let mut project = Project::new(ProjectType::Cargo, dir.path().into());
+        assert!(project.load_metadata().is_err());
+        assert_eq!(project.last_cleaned, None);
+    }
+
+    #[test]
+    fn turborepo_wins_over_package_json_and_cleans_node_modules() {
+        let dir = TempDir::new();
+        dir.file("turbo.json", "{}");
+        dir.file("package.json", "{}");
+        dir.file("node_modules/left-pad/index.js", "module.exports = 1");
+        dir.file(".turbo/cache/a", "a");
+
+        let project = Project::detect_in(dir.path()).unwrap();
+        assert!(matches!(project.project_type, ProjectType::Turborepo));
+
+        project
+            .clean_with_options(&CleanOptions::default())
+            .unwrap();
+        assert!(!dir.path().join("node_modules").exists());
+        assert!(!dir.path().join(".turbo").exists());
+    }
+
+    #[test]
+    fn dir_size_streaming_reports_running_totals() {
+        let dir = TempDir::new();
+        for i in 0..2500 {
+            dir.file(&format!("{}/{}", i % 10, i), "0123");
+        }
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let total = dir_size_streaming(dir.path(), sender);
+        let updates: Vec<u64> = receiver.iter().collect();
+
+        assert_eq!(total, dir_size(dir.path()));
+        assert_eq!(total, 10_000);
+        assert_eq!(updates, [4000, 8000, 10_000]);
+    }
+
+    #[test]
+    fn dir_size_streaming_survives_dropped_receiver() {
+        let dir = TempDir::new();
+        for i in 0..1200 {
+            dir.file(&i.to_string(), "01");
+        }
+        let (sender, receiver) = std::sync::mpsc::channel();
+        drop(receiver);
+        assert_eq!(dir_size_streaming(dir.path(), sender), 2400);
+    }
+
+    #[test]
+    fn artifact_snapshot_diff_and_round_trip() {
+        let dir = TempDir::new();
+        dir.file("Cargo.toml", "[package]");
+        dir.file("target/debug/kept", "kept");
+        let changed = dir.file("target/debug/changed", "old");
+        dir.file("target/debug/removed", "removed");
+        let project = Project::detect_in(dir.path()).unwrap();
+
+        let snapshot = project.snapshot_artifact_hash();
+        assert_eq!(snapshot.file_count(), 3);
+        let parsed: ArtifactSnapshot = snapshot.to_string().parse().unwrap();
+        assert_eq!(parsed, snapshot);
+        assert_eq!(parsed.hash(), snapshot.hash());
+        assert!(project.diff_artifact_snapshot(&snapshot).is_empty());
+
+        fs::write(&changed, "new contents").unwrap();
+        fs::remove_file(dir.path().join("target/debug/removed")).unwrap();
+        dir.file("target/debug/added", "added");
+        let diff = project.diff_artifact_snapshot(&parsed);
+        let debug = path::Path::new("target/debug");
+        assert_eq!(diff.added, [debug.join("added")]);
+        assert_eq!(diff.removed, [debug.join("removed")]);
+        assert_eq!(diff.modified, [debug.join("changed")]);
+    }
+
+    #[test]
+    fn artifact_snapshot_rejects_bad_nanos() {
+        assert!("1 2 999999999 a".parse::<ArtifactSnapshot>().is_ok());
+        assert!("1 2 1000000000 a".parse::<ArtifactSnapshot>().is_err());
+        assert!("1 2 4294967296 a".parse::<ArtifactSnapshot>().is_err());
+        assert!("1 2 a".parse::<ArtifactSnapshot>().is_err());
     }
 }
@@ -37,7 +37,7 @@ fn prepare_directories(dirs: Vec<PathBuf>) -> Result<Vec<PathBuf>, Error> {
     }
 
     dirs.into_par_iter()
-        .map(|d| path_canonicalise(&cd, d))
+        .map(|d| Ok(path_canonicalise(&cd, d)?))
         .collect()
 }
 
@@ -62,12 +62,22 @@ fn main() -> Result<(), Error> {
 
     let mut clean_all = opt.all;
 
-    'project_loop: for project in dirs.iter().flat_map(scan).filter_map(|p| p.ok()) {
+    'project_loop: for project in dirs.iter().flat_map(scan) {
+        let project = match project {
+            Ok(project) => project,
+            Err(e) => {
+                // network filesystem warnings land here too, the scan carries on regardless
+                if opt.quiet < 2 {
+                    eprintln!("{}", e);
+                }
+                continue;
+            }
+        };
         write_buffer.clear();
 
         let project_artifact_bytes = project
             .artifact_dirs()
-            .par_iter()
+            .iter()
             .copied()
             .filter_map(|dir| match dir_size(&project.path.join(dir)) {
                 0 => None,